@@ -0,0 +1,99 @@
+//! Decoding time for long chat streams, like those of reasoning models with thousands of deltas.
+//!
+//! Run with `cargo bench --bench stream`. The time per event should stay flat as streams grow.
+use std::time::{Duration, Instant};
+
+use bytes::Bytes;
+use futures_util::{stream, StreamExt};
+use openai_rust::middleware::{from_fn, Body, Response};
+use openai_rust::sse::Decoder;
+use openai_rust2 as openai_rust;
+
+/// A typical network chunk size.
+const CHUNK: usize = 1400;
+
+/// A stream of `deltas` content chunks, built from the hand-written OpenAI-style stream
+/// in `tests/fixtures/sse`.
+fn synthetic_stream(deltas: usize) -> Vec<u8> {
+    let fixture = include_str!("../tests/fixtures/sse/synthetic_openai.txt");
+    let mut events = fixture.split_inclusive("\n\n");
+    let first = events.next().unwrap();
+    let delta = events.next().unwrap();
+    let rest = events.skip(6).collect::<String>();
+    let mut body = first.to_owned();
+    for _ in 0..deltas {
+        body.push_str(delta);
+    }
+    body.push_str(&rest);
+    body.into_bytes()
+}
+
+fn decode(body: &[u8]) -> usize {
+    let mut decoder = Decoder::new();
+    let mut events = 0;
+    for chunk in body.chunks(CHUNK) {
+        decoder.push(chunk);
+        while decoder.next_event().is_some() {
+            events += 1;
+        }
+    }
+    events + decoder.finish().iter().count()
+}
+
+/// A client that answers every request with `body`.
+fn client(body: &[u8]) -> openai_rust::Client {
+    let chunks = body
+        .chunks(CHUNK)
+        .map(Bytes::copy_from_slice)
+        .collect::<Vec<_>>();
+    openai_rust::Client::builder()
+        .with_middleware(from_fn(move |_, _| {
+            let chunks = chunks.clone();
+            Box::pin(async move {
+                let mut res = Response::new(200, "");
+                res.body = Body::Stream(stream::iter(chunks.into_iter().map(Ok)).boxed());
+                Ok(res)
+            })
+        }))
+        .build()
+        .unwrap()
+}
+
+async fn chat_stream(client: &openai_rust::Client) -> usize {
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o-mini", vec![]);
+    let mut stream = client.create_chat_stream(args, None).await.unwrap();
+    let mut chunks = 0;
+    while let Some(chunk) = stream.next().await {
+        chunk.unwrap();
+        chunks += 1;
+    }
+    chunks
+}
+
+fn report(name: &str, deltas: usize, bytes: usize, elapsed: Duration) {
+    println!(
+        "{:<12} {:>7} deltas {:>9} bytes {:>9.2?} {:>8.0} ns/delta {:>7.1} MB/s",
+        name,
+        deltas,
+        bytes,
+        elapsed,
+        elapsed.as_nanos() as f64 / deltas as f64,
+        bytes as f64 / elapsed.as_secs_f64() / 1e6,
+    );
+}
+
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    for deltas in [1_000, 10_000, 100_000] {
+        let body = synthetic_stream(deltas);
+
+        let start = Instant::now();
+        assert_eq!(decode(&body), deltas + 3);
+        report("sse::Decoder", deltas, body.len(), start.elapsed());
+
+        let client = client(&body);
+        let start = Instant::now();
+        assert_eq!(runtime.block_on(chat_stream(&client)), deltas + 2);
+        report("chat stream", deltas, body.len(), start.elapsed());
+    }
+}
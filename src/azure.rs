@@ -0,0 +1,82 @@
+//! Azure OpenAI support.
+//!
+//! In Azure mode the base URL is the resource endpoint, requests go to
+//! `/openai/deployments/{deployment}/...?api-version=...` and the key is sent in the
+//! `api-key` header (or as a bearer token when using Microsoft Entra ID).
+//!
+//! ```
+//! use openai_rust2::azure::AzureConfig;
+//!
+//! let client = openai_rust2::Client::builder()
+//!     .with_base_url("https://my-resource.openai.azure.com")
+//!     .with_api_key("azure-api-key")
+//!     .with_azure(AzureConfig::new("2024-10-21").with_deployment("gpt-4o", "my-gpt-4o"))
+//!     .build()?;
+//! # Ok::<(), openai_rust2::Error>(())
+//! ```
+use std::collections::HashMap;
+
+/// How the key is sent to Azure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AzureAuth {
+    /// Send the key in the `api-key` header.
+    #[default]
+    ApiKey,
+    /// Send the key as a Microsoft Entra ID bearer token.
+    EntraId,
+}
+
+/// Configuration for talking to Azure OpenAI, see [ClientBuilder::with_azure](crate::ClientBuilder::with_azure).
+#[derive(Debug, Clone)]
+pub struct AzureConfig {
+    /// The `api-version` query parameter, e.g. `2024-10-21`.
+    pub api_version: String,
+    /// Maps model names to deployment names.
+    /// Models without an entry are assumed to be deployed under their own name.
+    pub deployments: HashMap<String, String>,
+    pub auth: AzureAuth,
+}
+
+impl AzureConfig {
+    pub fn new(api_version: impl Into<String>) -> Self {
+        Self {
+            api_version: api_version.into(),
+            deployments: HashMap::new(),
+            auth: AzureAuth::ApiKey,
+        }
+    }
+
+    /// Route requests for `model` to `deployment`.
+    pub fn with_deployment(
+        mut self,
+        model: impl Into<String>,
+        deployment: impl Into<String>,
+    ) -> Self {
+        self.deployments.insert(model.into(), deployment.into());
+        self
+    }
+
+    pub fn with_auth(mut self, auth: AzureAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// The deployment name for `model`.
+    pub fn deployment<'a>(&'a self, model: &'a str) -> &'a str {
+        self.deployments.get(model).map_or(model, String::as_str)
+    }
+
+    /// The path of `endpoint` below `base_path`.
+    /// Endpoints that are served per deployment pass the `model` they are called with.
+    pub(crate) fn path(&self, base_path: &str, endpoint: &str, model: Option<&str>) -> String {
+        match model {
+            Some(model) => format!(
+                "{}/openai/deployments/{}/{}",
+                base_path,
+                self.deployment(model),
+                endpoint
+            ),
+            None => format!("{}/openai/{}", base_path, endpoint),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+
+use crate::azure::{AzureAuth, AzureConfig};
+use crate::cassette::Cassette;
+use crate::chat::stream::StreamTimeouts;
+use crate::credentials::{AuthHeader, CredentialProvider, StaticKey};
+use crate::middleware::Middleware;
+use crate::rate_limit::RateLimiter;
+use crate::{retry::RetryPolicy, Client, Error, Result, DEFAULT_BASE_URL};
+
+/// Fallible, configurable construction of a [Client].
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let client = openai_rust2::Client::builder()
+///     .with_api_key("sk-...")
+///     .with_base_url("https://api.openai.com/v1")
+///     .with_organization("org-...")
+///     .with_project("proj_...")
+///     .with_header("X-Team", "search")
+///     .with_connect_timeout(Duration::from_secs(5))
+///     .with_timeout(Duration::from_secs(120))
+///     .with_user_agent("my-service/1.0")
+///     .build()?;
+/// # Ok::<(), openai_rust2::Error>(())
+/// ```
+#[derive(Default)]
+pub struct ClientBuilder {
+    api_key: String,
+    credentials: Option<Arc<dyn CredentialProvider>>,
+    base_url: Option<String>,
+    organization: Option<String>,
+    project: Option<String>,
+    headers: Vec<(String, String)>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    stream_timeouts: StreamTimeouts,
+    user_agent: Option<String>,
+    proxy: Option<reqwest::Proxy>,
+    http_client: Option<reqwest::Client>,
+    retry_policy: RetryPolicy,
+    azure: Option<AzureConfig>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    cassette: Option<Cassette>,
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The key sent as a bearer token. Leave empty for servers that need no authentication.
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = api_key.into();
+        self
+    }
+
+    /// Ask `provider` for the credential before every request, instead of sending a fixed key.
+    /// Takes precedence over [with_api_key](Self::with_api_key).
+    pub fn with_credentials(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    /// Defaults to `https://api.openai.com/v1`.
+    ///
+    /// Endpoint paths are appended to the base URL, so gateways and reverse proxies with
+    /// a path prefix like `https://gateway.corp/openai/v1` work. A base URL without a path,
+    /// like `http://localhost:11434`, is treated as `http://localhost:11434/v1`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Sent as the `OpenAI-Organization` header.
+    pub fn with_organization(mut self, organization: impl Into<String>) -> Self {
+        self.organization = Some(organization.into());
+        self
+    }
+
+    /// Sent as the `OpenAI-Project` header.
+    pub fn with_project(mut self, project: impl Into<String>) -> Self {
+        self.project = Some(project.into());
+        self
+    }
+
+    /// A header sent with every request. Invalid names or values are reported by [build](Self::build).
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Timeout for the whole request, from connecting until the response body has been read.
+    /// For streaming calls this includes the full stream.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Timeout for establishing the connection.
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Deadlines for the chunks of every streamed response.
+    /// Individual streams can override them with
+    /// [ChatCompletionChunkStream::with_timeouts](crate::chat::stream::ChatCompletionChunkStream::with_timeouts).
+    pub fn with_stream_timeouts(mut self, timeouts: StreamTimeouts) -> Self {
+        self.stream_timeouts = timeouts;
+        self
+    }
+
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    pub fn with_proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Use an existing `reqwest::Client`.
+    /// It cannot be combined with [with_connect_timeout](Self::with_connect_timeout) or [with_proxy](Self::with_proxy),
+    /// configure those on the `reqwest::Client` instead.
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = Some(http_client);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Talk to Azure OpenAI. The base URL must be the resource endpoint,
+    /// e.g. `https://my-resource.openai.azure.com`.
+    ///
+    /// [AzureConfig::auth] decides how the key from [with_api_key](Self::with_api_key) is sent.
+    /// A provider from [with_credentials](Self::with_credentials) picks its own [AuthHeader].
+    pub fn with_azure(mut self, azure: AzureConfig) -> Self {
+        self.azure = Some(azure);
+        self
+    }
+
+    /// Append `middleware` to the chain every request runs through.
+    /// Middleware added first runs first, see [middleware](crate::middleware).
+    pub fn with_middleware(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Record or replay every request with `cassette`, see [cassette](crate::cassette).
+    /// The cassette runs after all other middleware, so it sees requests as they are sent.
+    pub fn with_cassette(mut self, cassette: Cassette) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Enforce per-model request and token budgets, see [rate_limit](crate::rate_limit).
+    pub fn with_rate_limiter(mut self, rate_limiter: RateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let base_url = match &self.base_url {
+            Some(base_url) => reqwest::Url::parse(base_url)
+                .ok()
+                .filter(|url| !url.cannot_be_a_base())
+                .ok_or_else(|| Error::Config(format!("invalid base URL {:?}", base_url)))?,
+            None => DEFAULT_BASE_URL.clone(),
+        };
+
+        let mut default_headers = HeaderMap::new();
+        let org_and_project = [
+            ("OpenAI-Organization", self.organization),
+            ("OpenAI-Project", self.project),
+        ];
+        let user_agent = self
+            .user_agent
+            .map(|ua| (reqwest::header::USER_AGENT.as_str(), ua));
+        for (name, value) in org_and_project
+            .into_iter()
+            .filter_map(|(name, value)| value.map(|value| (name, value)))
+            .chain(user_agent)
+            .chain(self.headers.iter().map(|(n, v)| (n.as_str(), v.clone())))
+        {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| Error::Config(format!("invalid header name {:?}: {}", name, e)))?;
+            let value = HeaderValue::from_str(&value)
+                .map_err(|e| Error::Config(format!("invalid value for header {}: {}", name, e)))?;
+            default_headers.insert(name, value);
+        }
+
+        let req_client = match self.http_client {
+            Some(_) if self.connect_timeout.is_some() || self.proxy.is_some() => {
+                return Err(Error::Config(
+                    "a connect timeout or proxy cannot be applied to a supplied reqwest::Client"
+                        .to_owned(),
+                ))
+            }
+            Some(http_client) => http_client,
+            None => {
+                let mut builder = reqwest::ClientBuilder::new();
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(proxy) = self.proxy {
+                    builder = builder.proxy(proxy);
+                }
+                builder.build().map_err(|e| Error::Config(e.to_string()))?
+            }
+        };
+
+        let credentials = self.credentials.unwrap_or_else(|| {
+            let header = match &self.azure {
+                Some(azure) if azure.auth == AzureAuth::ApiKey => AuthHeader::ApiKey,
+                _ => AuthHeader::Bearer,
+            };
+            Arc::new(StaticKey::new(self.api_key).with_header(header))
+        });
+
+        let mut middleware = self.middleware;
+        if let Some(cassette) = self.cassette {
+            middleware.push(Arc::new(cassette));
+        }
+
+        Ok(Client {
+            req_client,
+            credentials,
+            base_url,
+            default_headers,
+            timeout: self.timeout,
+            stream_timeouts: self.stream_timeouts,
+            retry_policy: self.retry_policy,
+            azure: self.azure,
+            middleware,
+            rate_limiter: self.rate_limiter,
+        })
+    }
+}
@@ -0,0 +1,269 @@
+//! Record and replay HTTP interactions for offline, deterministic tests.
+//!
+//! In [Mode::Record] every request is sent and the request/response pair is appended to a
+//! JSON file. In [Mode::Replay] nothing is sent: requests are answered from the file by
+//! matching the method, the path and the JSON body (ignoring key order and whitespace).
+//! A request without a recorded interaction fails with [Error::Cassette].
+//!
+//! Streamed responses are recorded as the full SSE text and replayed as a stream.
+//! The host and query of the URL are not matched, so a cassette recorded against
+//! `https://api.openai.com/v1` replays for any base URL ending in `/v1`.
+//! Credentials are added after the cassette, so they are never written to the file.
+//!
+//! ```no_run
+//! use openai_rust2::cassette::{Cassette, Mode};
+//!
+//! let mode = if std::env::var("RECORD").is_ok() { Mode::Record } else { Mode::Replay };
+//! let client = openai_rust2::Client::builder()
+//!     .with_api_key(std::env::var("OPENAI_API_KEY").unwrap_or_default())
+//!     .with_cassette(Cassette::new("tests/cassettes/chat.json", mode)?)
+//!     .build()?;
+//! # Ok::<(), openai_rust2::Error>(())
+//! ```
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::middleware::{Body, Middleware, Next, Request, Response};
+use crate::{Error, Result};
+
+/// Whether a [Cassette] talks to the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Send requests and append every interaction to the file.
+    Record,
+    /// Answer requests from the file without sending them.
+    Replay,
+}
+
+/// A file of recorded interactions, see the [module documentation](self).
+///
+/// Add it to a client with [ClientBuilder::with_cassette](crate::ClientBuilder::with_cassette).
+#[derive(Debug)]
+pub struct Cassette {
+    path: PathBuf,
+    mode: Mode,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    interactions: Vec<Interaction>,
+    /// Which interactions have been replayed, so repeated requests get successive answers.
+    replayed: Vec<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct CassetteFile {
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct RecordedRequest {
+    method: String,
+    path: String,
+    #[serde(default)]
+    body: Option<Value>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    /// The body if it is JSON.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+    /// The body if it is not JSON, e.g. a server-sent event stream.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+impl Cassette {
+    /// Open the cassette at `path`.
+    /// In [Mode::Replay] the file is read now and must exist, in [Mode::Record] it is overwritten.
+    pub fn new(path: impl Into<PathBuf>, mode: Mode) -> Result<Self> {
+        let path = path.into();
+        let interactions = match mode {
+            Mode::Record => Vec::new(),
+            Mode::Replay => {
+                let file = std::fs::read(&path).map_err(|e| {
+                    Error::Cassette(format!("cannot read {}: {}", path.display(), e))
+                })?;
+                serde_json::from_slice::<CassetteFile>(&file)
+                    .map_err(|e| Error::Cassette(format!("invalid {}: {}", path.display(), e)))?
+                    .interactions
+            }
+        };
+        Ok(Self {
+            path,
+            mode,
+            state: Mutex::new(State {
+                replayed: vec![false; interactions.len()],
+                interactions,
+            }),
+        })
+    }
+
+    /// A cassette that replays the interactions at `path`.
+    pub fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::new(path, Mode::Replay)
+    }
+
+    /// A cassette that records to `path`.
+    pub fn record(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::new(path, Mode::Record)
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn find(&self, req: &RecordedRequest, stream: bool) -> Result<Response> {
+        let mut state = self.state.lock().unwrap();
+        let matching = |i: &usize| state.interactions[*i].request == *req;
+        let index = (0..state.interactions.len())
+            .filter(matching)
+            .find(|i| !state.replayed[*i])
+            // Once every match has been used, keep answering with the last one.
+            .or_else(|| (0..state.interactions.len()).rev().find(matching))
+            .ok_or_else(|| {
+                Error::Cassette(format!(
+                    "no recorded interaction for {} {} in {}",
+                    req.method,
+                    req.path,
+                    self.path.display()
+                ))
+            })?;
+        state.replayed[index] = true;
+        state.interactions[index].response.to_response(stream)
+    }
+
+    async fn record_interaction(&self, req: RecordedRequest, res: Response) -> Result<Response> {
+        let stream = matches!(res.body, Body::Stream(_));
+        let bytes = res.body.bytes().await?;
+        let response = RecordedResponse::new(res.status, &res.headers, &bytes);
+
+        let file = {
+            let mut state = self.state.lock().unwrap();
+            state.interactions.push(Interaction {
+                request: req,
+                response,
+            });
+            state.replayed.push(true);
+            CassetteFile {
+                interactions: state.interactions.clone(),
+            }
+        };
+        let json = serde_json::to_vec_pretty(&file).map_err(Error::Encode)?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| self.write_error(e))?;
+        }
+        std::fs::write(&self.path, json).map_err(|e| self.write_error(e))?;
+
+        Ok(Response {
+            status: res.status,
+            headers: res.headers,
+            body: if stream {
+                Body::Stream(Body::Bytes(bytes).into_stream())
+            } else {
+                Body::Bytes(bytes)
+            },
+        })
+    }
+
+    fn write_error(&self, e: std::io::Error) -> Error {
+        Error::Cassette(format!("cannot write {}: {}", self.path.display(), e))
+    }
+}
+
+impl Middleware for Cassette {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        async move {
+            let recorded = RecordedRequest {
+                method: req.method.to_string(),
+                path: req.url.path().to_owned(),
+                body: req.json::<Value>()?,
+            };
+            match self.mode {
+                Mode::Replay => self.find(&recorded, req.stream),
+                Mode::Record => {
+                    let res = next.run(req).await?;
+                    self.record_interaction(recorded, res).await
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+impl RecordedResponse {
+    fn new(status: StatusCode, headers: &HeaderMap, bytes: &[u8]) -> Self {
+        let headers = headers
+            .iter()
+            // The body is stored re-encoded, and cookies have no place in a fixture.
+            .filter(|(name, _)| {
+                ![
+                    reqwest::header::CONTENT_LENGTH,
+                    reqwest::header::TRANSFER_ENCODING,
+                    reqwest::header::SET_COOKIE,
+                ]
+                .contains(name)
+            })
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_owned())))
+            .collect();
+        let (body, text) = match serde_json::from_slice::<Value>(bytes) {
+            Ok(body) => (Some(body), None),
+            Err(_) if bytes.is_empty() => (None, None),
+            Err(_) => (None, Some(String::from_utf8_lossy(bytes).into_owned())),
+        };
+        Self {
+            status: status.as_u16(),
+            headers,
+            body,
+            text,
+        }
+    }
+
+    /// `stream` replays a successful body as [Body::Stream], as the transport would.
+    fn to_response(&self, stream: bool) -> Result<Response> {
+        let invalid =
+            |what: String| Error::Cassette(format!("invalid recorded response: {}", what));
+        let status = StatusCode::from_u16(self.status).map_err(|e| invalid(e.to_string()))?;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+            let value = HeaderValue::from_str(value).map_err(|e| invalid(e.to_string()))?;
+            headers.append(name, value);
+        }
+        let bytes = match (&self.body, &self.text) {
+            (Some(body), _) => serde_json::to_vec(body).map_err(Error::Encode)?,
+            (None, Some(text)) => text.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        let body = Body::Bytes(bytes.into());
+        Ok(Response {
+            status,
+            headers,
+            body: if stream && status.is_success() {
+                Body::Stream(body.into_stream())
+            } else {
+                body
+            },
+        })
+    }
+}
@@ -1,9 +1,74 @@
+use std::collections::HashMap;
+
+use base64::prelude::{Engine, BASE64_STANDARD};
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(feature = "schemars")]
+pub use schema::strict_schema;
+mod tool_runner;
+pub use tool_runner::{LocalTool, ToolCallRecord, ToolOutcome, ToolRun, ToolRunner, ToolStep};
+
+/// The format the model must answer in.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ResponseFormat {
+    /// Any valid JSON. The prompt must still ask for JSON.
     JsonObject,
     Text,
+    /// JSON matching a schema, also called structured outputs.
+    JsonSchema {
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct JsonSchemaFormat {
+    /// Letters, digits, `_` and `-`.
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub schema: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+impl ResponseFormat {
+    /// Strict JSON matching `schema`, which must follow the rules of strict mode,
+    /// see [strict_schema] with the `schemars` feature.
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: Some(true),
+            },
+        }
+    }
+
+    /// JSON that deserializes into a `T`, named after the type.
+    /// Strict unless `T` contains a map, see [strict_schema].
+    #[cfg(feature = "schemars")]
+    pub fn typed<T: schemars::JsonSchema>() -> ResponseFormat {
+        let name = T::schema_name()
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+                _ => '_',
+            })
+            .collect::<String>();
+        let (schema, strict) = schema::typed_schema::<T>();
+        ResponseFormat::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name,
+                description: None,
+                schema,
+                strict: Some(strict),
+            },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -13,7 +78,7 @@ pub struct ImageGeneration {
     pub output_format: Option<String>, // e.g., "base64", "url"
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, Default)]
 pub struct ChatArguments {
     pub model: String,
     pub messages: Vec<Message>,
@@ -23,16 +88,60 @@ pub struct ChatArguments {
     pub top_p: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub n: Option<u32>,
+    /// Set by [Client::create_chat_stream](crate::Client::create_chat_stream).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
+    /// Only sent for streamed requests, [Client::create_chat](crate::Client::create_chat) drops it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub stop: Option<String>,
+    pub stop: Option<Stop>,
+    /// Deprecated by OpenAI in favor of `max_completion_tokens`, and not supported by reasoning models.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
+    /// Limit on the generated tokens, including reasoning tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_completion_tokens: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub presence_penalty: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub frequency_penalty: Option<f32>,
+    /// Best-effort deterministic sampling, see [ChatCompletion::system_fingerprint].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<i64>,
+    /// Bias from -100 to 100 added to the logits of token ids.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logit_bias: Option<HashMap<u32, i32>>,
+    /// Return the log probability of each output token in [Choice::logprobs].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logprobs: Option<bool>,
+    /// Also return the most likely alternatives at each position, up to 20. Requires `logprobs`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_logprobs: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verbosity: Option<Verbosity>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_tier: Option<ServiceTier>,
+    /// Whether OpenAI stores the completion for distillation and evals.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<bool>,
+    /// Up to 16 key-value pairs attached to a stored completion.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modalities: Option<Vec<Modality>>,
+    /// Known parts of the answer, which speed up generation when they match.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prediction: Option<Prediction>,
+    /// Groups requests that share a prompt prefix to improve cache hits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt_cache_key: Option<String>,
+    /// A stable, hashed identifier of the end user, used to detect abuse.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub safety_identifier: Option<String>,
+    /// Deprecated by OpenAI in favor of `safety_identifier` and `prompt_cache_key`.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -44,12 +153,17 @@ pub struct ChatArguments {
     /// See: https://docs.x.ai/docs/guides/tools/overview
     #[serde(skip_serializing_if = "Option::is_none", rename = "server_tools")]
     pub grok_tools: Option<Vec<GrokTool>>,
-    /// OpenAI Agent Tools API - server-side tools for agentic capabilities (Responses API only).
-    /// Includes: web_search, file_search, code_interpreter.
-    /// Note: When tools are provided, use create_openai_responses() to use the Responses API endpoint.
-    /// See: https://platform.openai.com/docs/guides/tools-web-search
+    /// Functions the model may call, see [Tool::function].
+    ///
+    /// OpenAI server-side tools (web_search, file_search, code_interpreter) are only supported
+    /// by the Responses API, see [with_openai_tools](Self::with_openai_tools).
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tools: Option<Vec<OpenAITool>>,
+    pub tools: Option<Vec<Tool>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    /// Whether the model may call several tools in one turn. Defaults to true on the server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parallel_tool_calls: Option<bool>,
 }
 
 impl ChatArguments {
@@ -57,22 +171,140 @@ impl ChatArguments {
         ChatArguments {
             model: model.as_ref().to_owned(),
             messages,
-            temperature: None,
-            top_p: None,
-            n: None,
-            stream: None,
-            stop: None,
-            max_tokens: None,
-            presence_penalty: None,
-            frequency_penalty: None,
-            user: None,
-            response_format: None,
-            image_generation: None,
-            grok_tools: None,
-            tools: None,
+            ..Default::default()
         }
     }
 
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_top_p(mut self, top_p: f32) -> Self {
+        self.top_p = Some(top_p);
+        self
+    }
+
+    /// The number of choices to generate.
+    pub fn with_n(mut self, n: u32) -> Self {
+        self.n = Some(n);
+        self
+    }
+
+    /// One stop sequence, or a `Vec` of up to four.
+    pub fn with_stop(mut self, stop: impl Into<Stop>) -> Self {
+        self.stop = Some(stop.into());
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_max_completion_tokens(mut self, max_completion_tokens: u32) -> Self {
+        self.max_completion_tokens = Some(max_completion_tokens);
+        self
+    }
+
+    pub fn with_presence_penalty(mut self, presence_penalty: f32) -> Self {
+        self.presence_penalty = Some(presence_penalty);
+        self
+    }
+
+    pub fn with_frequency_penalty(mut self, frequency_penalty: f32) -> Self {
+        self.frequency_penalty = Some(frequency_penalty);
+        self
+    }
+
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_logit_bias(mut self, logit_bias: HashMap<u32, i32>) -> Self {
+        self.logit_bias = Some(logit_bias);
+        self
+    }
+
+    pub fn with_logprobs(mut self, logprobs: bool) -> Self {
+        self.logprobs = Some(logprobs);
+        self
+    }
+
+    /// Also turns on [logprobs](Self::logprobs).
+    pub fn with_top_logprobs(mut self, top_logprobs: u32) -> Self {
+        self.logprobs = Some(true);
+        self.top_logprobs = Some(top_logprobs);
+        self
+    }
+
+    pub fn with_reasoning_effort(mut self, reasoning_effort: ReasoningEffort) -> Self {
+        self.reasoning_effort = Some(reasoning_effort);
+        self
+    }
+
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = Some(verbosity);
+        self
+    }
+
+    pub fn with_service_tier(mut self, service_tier: ServiceTier) -> Self {
+        self.service_tier = Some(service_tier);
+        self
+    }
+
+    pub fn with_store(mut self, store: bool) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: HashMap<String, String>) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn with_modalities(mut self, modalities: Vec<Modality>) -> Self {
+        self.modalities = Some(modalities);
+        self
+    }
+
+    pub fn with_prediction(mut self, prediction: Prediction) -> Self {
+        self.prediction = Some(prediction);
+        self
+    }
+
+    pub fn with_prompt_cache_key(mut self, prompt_cache_key: impl Into<String>) -> Self {
+        self.prompt_cache_key = Some(prompt_cache_key.into());
+        self
+    }
+
+    pub fn with_safety_identifier(mut self, safety_identifier: impl Into<String>) -> Self {
+        self.safety_identifier = Some(safety_identifier.into());
+        self
+    }
+
+    pub fn with_user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    pub fn with_image_generation(mut self, image_generation: ImageGeneration) -> Self {
+        self.image_generation = Some(image_generation);
+        self
+    }
+
+    /// Options for [Client::create_chat_stream](crate::Client::create_chat_stream).
+    pub fn with_stream_options(mut self, options: StreamOptions) -> Self {
+        self.stream_options = Some(options);
+        self
+    }
+
     /// Add xAI server-side tools for agentic capabilities.
     /// Recommended model: `grok-4-1-fast` for best tool-calling performance.
     pub fn with_grok_tools(mut self, tools: Vec<GrokTool>) -> Self {
@@ -84,9 +316,110 @@ impl ChatArguments {
     /// Note: When tools are provided, use create_openai_responses() to use the Responses API endpoint.
     /// Recommended models: `gpt-5`, `gpt-4o`.
     pub fn with_openai_tools(mut self, tools: Vec<OpenAITool>) -> Self {
+        self.tools = Some(tools.into_iter().map(Tool::OpenAI).collect());
+        self
+    }
+
+    /// Functions the model may call. The calls come back in [Message::tool_calls],
+    /// answer them with [Message::tool_result] messages.
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
         self.tools = Some(tools);
         self
     }
+
+    pub fn with_tool_choice(mut self, tool_choice: ToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.parallel_tool_calls = Some(parallel);
+        self
+    }
+}
+
+/// Up to four sequences where the model stops generating.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Stop {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<String> for Stop {
+    fn from(stop: String) -> Self {
+        Stop::One(stop)
+    }
+}
+
+impl From<&str> for Stop {
+    fn from(stop: &str) -> Self {
+        Stop::One(stop.to_owned())
+    }
+}
+
+impl From<Vec<String>> for Stop {
+    fn from(stop: Vec<String>) -> Self {
+        Stop::Many(stop)
+    }
+}
+
+impl From<Vec<&str>> for Stop {
+    fn from(stop: Vec<&str>) -> Self {
+        Stop::Many(stop.into_iter().map(str::to_owned).collect())
+    }
+}
+
+/// How much reasoning models think before answering.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningEffort {
+    Minimal,
+    Low,
+    Medium,
+    High,
+}
+
+/// How long the answer is.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    Low,
+    Medium,
+    High,
+}
+
+/// The processing tier of a request. The tier used is reported in [ChatCompletion::service_tier].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServiceTier {
+    Auto,
+    Default,
+    Flex,
+    Scale,
+    Priority,
+}
+
+/// An output type of the model.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Modality {
+    Text,
+    Audio,
+}
+
+/// Predicted output, see [ChatArguments::prediction].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Prediction {
+    /// Content the answer is expected to largely repeat, e.g. a file being edited.
+    Content { content: Content },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct StreamOptions {
+    /// Send a last chunk with the [Usage] of the whole request and no choices.
+    pub include_usage: bool,
 }
 
 #[derive(Deserialize, Debug, Clone)]
@@ -99,40 +432,117 @@ pub struct ChatCompletion {
     #[serde(default)]
     pub object: Option<String>,
     pub choices: Vec<Choice>,
+    /// All zero if the server did not report usage.
+    #[serde(default)]
     pub usage: Usage,
+    /// The backend configuration, changes of which can affect [seeded](ChatArguments::seed) results.
+    #[serde(default)]
+    pub system_fingerprint: Option<String>,
+    /// The tier that processed the request, e.g. `default` or `flex`.
+    #[serde(default)]
+    pub service_tier: Option<String>,
+}
+
+impl ChatCompletion {
+    /// Deserialize the content of the first choice, e.g. the answer to a
+    /// [ResponseFormat::JsonSchema] request. A refusal is returned as [Parsed::Refusal].
+    pub fn parse<T: serde::de::DeserializeOwned>(&self) -> crate::Result<Parsed<T>> {
+        let message = self.choices.first().map(|c| &c.message);
+        if let Some(refusal) = message.and_then(|m| m.refusal.clone()) {
+            return Ok(Parsed::Refusal(refusal));
+        }
+        let content = message
+            .and_then(|m| m.content.as_ref())
+            .map(Content::to_string)
+            .unwrap_or_default();
+        serde_json::from_str(&content)
+            .map(Parsed::Value)
+            .map_err(|e| crate::Error::decode(e, content.as_bytes()))
+    }
+}
+
+/// The outcome of [ChatCompletion::parse].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Parsed<T> {
+    Value(T),
+    /// The model declined to answer, with its explanation.
+    Refusal(String),
+}
+
+impl<T> Parsed<T> {
+    /// The value, `None` for a refusal.
+    pub fn value(self) -> Option<T> {
+        match self {
+            Parsed::Value(value) => Some(value),
+            Parsed::Refusal(_) => None,
+        }
+    }
+}
+
+/// The result of [Client::create_chat_parsed](crate::Client::create_chat_parsed).
+#[derive(Debug, Clone)]
+pub struct ParsedCompletion<T> {
+    pub parsed: Parsed<T>,
+    pub completion: ChatCompletion,
 }
 
 impl std::fmt::Display for ChatCompletion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self.choices[0].message.content)?;
+        if let Some(content) = &self.choices[0].message.content {
+            write!(f, "{}", content)?;
+        }
         Ok(())
     }
 }
 
 pub mod stream {
     use bytes::Bytes;
-    use futures_util::Stream;
+    use futures_util::stream::BoxStream;
+    use futures_util::{ready, Stream, StreamExt};
+    use reqwest::header::HeaderMap;
+    use reqwest::StatusCode;
     use serde::Deserialize;
+    use std::collections::VecDeque;
+    use std::future::Future;
     use std::pin::Pin;
-    use std::str;
-    use std::task::Poll;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Poll, Waker};
+    use std::time::{Duration, Instant};
+
+    use tokio::time::Sleep;
+
+    use super::{Logprobs, Usage};
+    use crate::error::ApiError;
+    use crate::rate_limit::Permit;
+    use crate::sse::{Decoder, Event};
+    use crate::telemetry::CallSpan;
+    use crate::{Error, Result};
 
     #[derive(Deserialize, Debug, Clone)]
     pub struct ChatCompletionChunk {
         pub id: String,
         pub created: u32,
         pub model: String,
+        /// Empty in the final chunk sent for [StreamOptions::include_usage](super::StreamOptions::include_usage).
+        #[serde(default)]
         pub choices: Vec<Choice>,
+        #[serde(default)]
         pub system_fingerprint: Option<String>,
+        #[serde(default)]
+        pub service_tier: Option<String>,
+        /// Only set in the final chunk, see [StreamOptions::include_usage](super::StreamOptions::include_usage).
+        #[serde(default)]
+        pub usage: Option<Usage>,
     }
 
+    /// The content of the first choice, empty if there is none.
     impl std::fmt::Display for ChatCompletionChunk {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            write!(
-                f,
-                "{}",
-                self.choices[0].delta.content.as_ref().unwrap_or(&"".into())
-            )?;
+            let content = self
+                .choices
+                .first()
+                .and_then(|c| c.delta.content.as_deref());
+            write!(f, "{}", content.unwrap_or_default())?;
             Ok(())
         }
     }
@@ -142,96 +552,658 @@ pub mod stream {
         pub delta: ChoiceDelta,
         pub index: u32,
         pub finish_reason: Option<String>,
+        #[serde(default)]
+        pub logprobs: Option<Logprobs>,
     }
 
-    #[derive(Deserialize, Debug, Clone)]
+    /// The fields of the message that a chunk adds. Text fields are fragments to append.
+    #[derive(Deserialize, Debug, Clone, Default)]
     pub struct ChoiceDelta {
+        /// Usually only sent in the first chunk of a choice.
+        #[serde(default)]
+        pub role: Option<super::Role>,
+        #[serde(default)]
         pub content: Option<String>,
+        #[serde(default)]
+        pub tool_calls: Option<Vec<ToolCallDelta>>,
+        #[serde(default)]
+        pub refusal: Option<String>,
+        /// The reasoning of models that expose it, e.g. DeepSeek and xAI.
+        #[serde(default)]
+        pub reasoning_content: Option<String>,
     }
 
+    /// A fragment of a tool call. Fragments with the same `index` belong to the same call:
+    /// the first carries the `id` and function name, the rest append to the arguments.
+    #[derive(Deserialize, Debug, Clone, Default)]
+    pub struct ToolCallDelta {
+        pub index: u32,
+        #[serde(default)]
+        pub id: Option<String>,
+        #[serde(default, rename = "type")]
+        pub kind: Option<String>,
+        #[serde(default)]
+        pub function: Option<FunctionCallDelta>,
+    }
+
+    #[derive(Deserialize, Debug, Clone, Default)]
+    pub struct FunctionCallDelta {
+        #[serde(default)]
+        pub name: Option<String>,
+        /// A fragment of the JSON arguments.
+        #[serde(default)]
+        pub arguments: Option<String>,
+    }
+
+    /// Deadlines for a streamed response, all measured on the chunks it yields.
+    /// Unset deadlines are not enforced.
+    ///
+    /// A missed deadline ends the stream with [Error::StreamTimeout].
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub struct StreamTimeouts {
+        pub first_chunk: Option<Duration>,
+        pub idle: Option<Duration>,
+        pub total: Option<Duration>,
+    }
+
+    impl StreamTimeouts {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Time from making the call until the first chunk.
+        /// Also applies while waiting for the response headers.
+        pub fn with_first_chunk(mut self, timeout: Duration) -> Self {
+            self.first_chunk = Some(timeout);
+            self
+        }
+
+        /// Longest gap between two chunks. Keep-alive comments do not count as chunks.
+        pub fn with_idle(mut self, timeout: Duration) -> Self {
+            self.idle = Some(timeout);
+            self
+        }
+
+        /// Time from making the call until the stream has ended.
+        pub fn with_total(mut self, timeout: Duration) -> Self {
+            self.total = Some(timeout);
+            self
+        }
+
+        /// The nearer of the first chunk and total deadlines, which bound the wait for the response headers.
+        pub(crate) fn response_deadline(&self) -> Option<(Duration, StreamDeadline)> {
+            let first_chunk = self
+                .first_chunk
+                .map(|timeout| (timeout, StreamDeadline::FirstChunk));
+            let total = self.total.map(|timeout| (timeout, StreamDeadline::Total));
+            first_chunk
+                .into_iter()
+                .chain(total)
+                .min_by_key(|(timeout, _)| *timeout)
+        }
+    }
+
+    /// Which of the [StreamTimeouts] was missed.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum StreamDeadline {
+        FirstChunk,
+        Idle,
+        Total,
+    }
+
+    /// Timing of a stream so far, see [ChatCompletionChunkStream::stats].
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub struct StreamStats {
+        /// Time from making the call until the first chunk.
+        pub time_to_first_chunk: Option<Duration>,
+        /// Time from making the call until the first chunk with content, reasoning,
+        /// a refusal or a tool call, as opposed to e.g. only a role.
+        pub time_to_first_token: Option<Duration>,
+        pub chunks: u64,
+        /// Time from making the call until now, or until the stream ended.
+        pub elapsed: Duration,
+    }
+
+    impl StreamStats {
+        /// Chunks per second after the first one, 0 until there are two.
+        pub fn chunks_per_second(&self) -> f64 {
+            let Some(first) = self.time_to_first_chunk else {
+                return 0.0;
+            };
+            let streaming = self.elapsed.saturating_sub(first).as_secs_f64();
+            if self.chunks < 2 || streaming == 0.0 {
+                return 0.0;
+            }
+            (self.chunks - 1) as f64 / streaming
+        }
+    }
+
+    /// The chunks of a streamed chat completion, see [Client::create_chat_stream](crate::Client::create_chat_stream).
+    ///
+    /// The stream is `Send` and `Unpin`, so it can be moved into a spawned task or held across
+    /// `.await` in a `Send` future.
     pub struct ChatCompletionChunkStream {
-        byte_stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>>>>,
-        buf: String,
+        byte_stream: BoxStream<'static, Result<Bytes>>,
+        decoder: Decoder,
+        /// The byte stream has ended.
+        eof: bool,
+        /// `[DONE]`, an error frame or the end of the byte stream was seen.
+        done: bool,
+        /// Closed when the stream ends.
+        span: Option<CallSpan>,
+        timeouts: StreamTimeouts,
+        /// Armed with the nearest deadline while waiting for bytes.
+        sleep: Option<Pin<Box<Sleep>>>,
+        started: Instant,
+        last_chunk: Option<Instant>,
+        ended: Option<Instant>,
+        stats: StreamStats,
+        /// Reconciled with the usage chunk, if the stream has one.
+        permit: Option<Permit>,
     }
 
     impl ChatCompletionChunkStream {
-        pub(crate) fn new(stream: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>>>>) -> Self {
+        /// `started` is when the call was made, the start of the first chunk and total deadlines.
+        pub(crate) fn new(
+            stream: BoxStream<'static, Result<Bytes>>,
+            span: CallSpan,
+            timeouts: StreamTimeouts,
+            started: Instant,
+            permit: Option<Permit>,
+        ) -> Self {
             Self {
                 byte_stream: stream,
-                buf: String::new(),
+                decoder: Decoder::new(),
+                eof: false,
+                done: false,
+                span: Some(span),
+                timeouts,
+                sleep: None,
+                started,
+                last_chunk: None,
+                ended: None,
+                stats: StreamStats::default(),
+                permit,
             }
         }
 
-        fn deserialize_buf(
-            self: Pin<&mut Self>,
+        /// Replace the deadlines set with [ClientBuilder::with_stream_timeouts](crate::ClientBuilder::with_stream_timeouts).
+        /// They still count from when the call was made, and the response headers have already arrived.
+        pub fn with_timeouts(mut self, timeouts: StreamTimeouts) -> Self {
+            self.timeouts = timeouts;
+            self
+        }
+
+        pub fn stats(&self) -> StreamStats {
+            StreamStats {
+                elapsed: self.ended.unwrap_or_else(Instant::now) - self.started,
+                ..self.stats
+            }
+        }
+
+        /// The nearest deadline still ahead.
+        fn deadline(&self) -> Option<(Instant, StreamDeadline)> {
+            let after = |timeout: Option<Duration>, from: Instant, kind| {
+                timeout.map(|timeout| (from + timeout, kind))
+            };
+            let gap = match self.last_chunk {
+                None => after(
+                    self.timeouts.first_chunk,
+                    self.started,
+                    StreamDeadline::FirstChunk,
+                ),
+                Some(last) => after(self.timeouts.idle, last, StreamDeadline::Idle),
+            };
+            let total = after(self.timeouts.total, self.started, StreamDeadline::Total);
+            gap.into_iter().chain(total).min_by_key(|(at, _)| *at)
+        }
+
+        /// The error for a passed deadline. Otherwise arms the timer to wake the task at the nearest one.
+        fn poll_deadline(&mut self, cx: &mut std::task::Context<'_>) -> Option<Error> {
+            let (at, kind) = self.deadline()?;
+            let at = tokio::time::Instant::from_std(at);
+            let sleep = self
+                .sleep
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(at)));
+            if sleep.deadline() != at {
+                sleep.as_mut().reset(at);
+            }
+            sleep
+                .as_mut()
+                .poll(cx)
+                .is_ready()
+                .then(|| Error::StreamTimeout {
+                    deadline: kind,
+                    elapsed: self.started.elapsed(),
+                })
+        }
+
+        fn record(&mut self, item: Option<&Result<ChatCompletionChunk>>) {
+            match item {
+                Some(Ok(chunk)) => {
+                    let now = Instant::now();
+                    self.last_chunk = Some(now);
+                    self.stats.chunks += 1;
+                    self.stats
+                        .time_to_first_chunk
+                        .get_or_insert(now - self.started);
+                    if self.stats.time_to_first_token.is_none() && has_tokens(chunk) {
+                        self.stats.time_to_first_token = Some(now - self.started);
+                    }
+                    if let Some(span) = &mut self.span {
+                        span.chunk(chunk);
+                    }
+                    if chunk.usage.is_some() {
+                        if let Some(permit) = self.permit.take() {
+                            permit.reconcile(chunk);
+                        }
+                    }
+                }
+                Some(Err(_)) => {}
+                None => {
+                    self.ended.get_or_insert_with(Instant::now);
+                    if let Some(span) = self.span.take() {
+                        span.finish();
+                    }
+                }
+            }
+        }
+
+        /// The chunk in `event`, or `None` for events that carry none.
+        fn parse_event(&mut self, event: Event) -> Option<Result<ChatCompletionChunk>> {
+            let data = event.data.trim();
+            if data == "[DONE]" {
+                self.done = true;
+                return None;
+            }
+            if data.is_empty() {
+                return None;
+            }
+            match serde_json::from_str::<ChatCompletionChunk>(data) {
+                Ok(chunk) if event.event.as_deref() != Some("error") => Some(Ok(chunk)),
+                res => {
+                    // An error sent after the response started, e.g. `{"error": {...}}`.
+                    let error = ApiError::new(StatusCode::OK, &HeaderMap::new(), data.as_bytes());
+                    if error.error.is_some() || event.event.as_deref() == Some("error") {
+                        self.done = true;
+                        return Some(Err(error.into()));
+                    }
+                    Some(res.map_err(|e| Error::decode(e, data.as_bytes())))
+                }
+            }
+        }
+
+        fn poll_chunk(
+            &mut self,
             cx: &mut std::task::Context<'_>,
-        ) -> Option<anyhow::Result<ChatCompletionChunk>> {
-            let bufclone = self.buf.clone();
-            let mut chunks = bufclone.split("\n\n").peekable();
-            let first = chunks.next();
-            let second = chunks.peek();
-
-            match first {
-                Some(first) => match first.strip_prefix("data: ") {
-                    Some(chunk) => {
-                        if !chunk.ends_with("}") {
-                            None
-                        } else {
-                            if let Some(second) = second {
-                                if second.ends_with("}") {
-                                    cx.waker().wake_by_ref();
-                                }
+        ) -> Poll<Option<Result<ChatCompletionChunk>>> {
+            while !self.done {
+                let event = match self.decoder.next_event() {
+                    Some(event) => event,
+                    None if self.eof => match self.decoder.finish() {
+                        Some(event) => event,
+                        None => break,
+                    },
+                    None => {
+                        // Checked before every read, so a steady trickle of bytes cannot outlast it.
+                        if let Some(e) = self.poll_deadline(cx) {
+                            self.done = true;
+                            return Poll::Ready(Some(Err(e)));
+                        }
+                        match ready!(self.byte_stream.poll_next_unpin(cx)) {
+                            Some(Ok(bytes)) => self.decoder.push(&bytes),
+                            Some(Err(e)) => {
+                                self.done = true;
+                                return Poll::Ready(Some(Err(e)));
                             }
-                            self.get_mut().buf = chunks.collect::<Vec<_>>().join("\n\n");
-                            Some(
-                                serde_json::from_str::<ChatCompletionChunk>(chunk)
-                                    .map_err(|e| anyhow::anyhow!(e)),
-                            )
+                            None => self.eof = true,
                         }
+                        continue;
                     }
-                    None => None,
-                },
-                None => None,
+                };
+                if let Some(chunk) = self.parse_event(event) {
+                    return Poll::Ready(Some(chunk));
+                }
             }
+            self.done = true;
+            Poll::Ready(None)
+        }
+    }
+
+    impl ChatCompletionChunkStream {
+        /// The deltas of every choice, in the order they arrive, with their choice index.
+        pub fn choice_deltas(
+            self,
+        ) -> impl Stream<Item = Result<(u32, ChoiceDelta)>> + Send + Unpin {
+            self.flat_map(|chunk| {
+                let deltas: Vec<_> = match chunk {
+                    Ok(chunk) => chunk
+                        .choices
+                        .into_iter()
+                        .map(|choice| Ok((choice.index, choice.delta)))
+                        .collect(),
+                    Err(e) => vec![Err(e)],
+                };
+                futures_util::stream::iter(deltas)
+            })
+        }
+
+        /// Split the stream into one text stream for each of the first `n` choices,
+        /// e.g. to show the candidates of a request with [ChatArguments::n](super::ChatArguments::n) side by side.
+        ///
+        /// The streams share the connection and can be polled concurrently or one after the other;
+        /// text for the other choices is buffered until they are polled. Each stream ends after its
+        /// choice's `finish_reason`. An error is returned by the stream that read it, the others end.
+        pub fn split_choices(self, n: usize) -> Vec<ChoiceStream> {
+            let demux = Arc::new(Mutex::new(Demux {
+                stream: self,
+                choices: (0..n).map(|_| DemuxChoice::default()).collect(),
+                done: false,
+            }));
+            (0..n)
+                .map(|index| ChoiceStream {
+                    index,
+                    demux: demux.clone(),
+                })
+                .collect()
+        }
+
+        /// Consume the stream and fold it into a [ChatCompletion](super::ChatCompletion),
+        /// see [ChatStreamAccumulator].
+        pub async fn collect_completion(mut self) -> Result<super::ChatCompletion> {
+            let mut accumulator = ChatStreamAccumulator::new();
+            while let Some(chunk) = self.next().await {
+                accumulator.push(&chunk?);
+            }
+            Ok(accumulator.finish())
         }
     }
 
     impl Stream for ChatCompletionChunkStream {
-        type Item = anyhow::Result<ChatCompletionChunk>;
+        type Item = Result<ChatCompletionChunk>;
 
         fn poll_next(
             mut self: Pin<&mut Self>,
             cx: &mut std::task::Context<'_>,
         ) -> Poll<Option<Self::Item>> {
-            if let Some(chunk) = self.as_mut().deserialize_buf(cx) {
-                return Poll::Ready(Some(chunk));
+            let res = self.poll_chunk(cx);
+            if let Poll::Ready(item) = &res {
+                self.record(item.as_ref());
             }
+            res
+        }
+    }
 
-            match self.byte_stream.as_mut().poll_next(cx) {
-                Poll::Ready(bytes_option) => match bytes_option {
-                    Some(bytes_result) => match bytes_result {
-                        Ok(bytes) => {
-                            let data = str::from_utf8(&bytes)?.to_owned();
-                            self.buf = self.buf.clone() + &data;
-                            match self.deserialize_buf(cx) {
-                                Some(chunk) => Poll::Ready(Some(chunk)),
-                                None => {
-                                    cx.waker().wake_by_ref();
-                                    Poll::Pending
-                                }
-                            }
+    /// Whether `chunk` carries part of the answer.
+    fn has_tokens(chunk: &ChatCompletionChunk) -> bool {
+        chunk.choices.iter().any(|choice| {
+            let delta = &choice.delta;
+            [&delta.content, &delta.reasoning_content, &delta.refusal]
+                .into_iter()
+                .any(|text| text.as_deref().is_some_and(|t| !t.is_empty()))
+                || delta.tool_calls.as_ref().is_some_and(|c| !c.is_empty())
+        })
+    }
+
+    /// The text of one choice, see [ChatCompletionChunkStream::split_choices].
+    pub struct ChoiceStream {
+        index: usize,
+        demux: Arc<Mutex<Demux>>,
+    }
+
+    impl ChoiceStream {
+        /// The choice index this stream follows.
+        pub fn index(&self) -> u32 {
+            self.index as u32
+        }
+    }
+
+    impl Drop for ChoiceStream {
+        fn drop(&mut self) {
+            if let Ok(mut demux) = self.demux.lock() {
+                demux.wake_all();
+            }
+        }
+    }
+
+    struct Demux {
+        stream: ChatCompletionChunkStream,
+        choices: Vec<DemuxChoice>,
+        /// The underlying stream has ended or failed.
+        done: bool,
+    }
+
+    #[derive(Default)]
+    struct DemuxChoice {
+        pending: VecDeque<String>,
+        finished: bool,
+        waker: Option<Waker>,
+    }
+
+    impl Demux {
+        /// Hand the content of `chunk` to the choices, waking the ones that got some.
+        fn dispatch(&mut self, chunk: ChatCompletionChunk) {
+            for choice in chunk.choices {
+                let Some(demux_choice) = self.choices.get_mut(choice.index as usize) else {
+                    continue;
+                };
+                let content = choice.delta.content.filter(|c| !c.is_empty());
+                let finished = choice.finish_reason.is_some();
+                if content.is_none() && !finished {
+                    continue;
+                }
+                demux_choice.pending.extend(content);
+                demux_choice.finished |= finished;
+                if let Some(waker) = demux_choice.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+
+        fn end(&mut self) {
+            self.done = true;
+            self.wake_all();
+        }
+
+        /// Let a waiting stream take over polling the connection, whose waker may belong to
+        /// a stream that stopped polling.
+        fn wake_all(&mut self) {
+            for choice in &mut self.choices {
+                if let Some(waker) = choice.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+    }
+
+    impl Stream for ChoiceStream {
+        type Item = Result<String>;
+
+        fn poll_next(
+            self: Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+        ) -> Poll<Option<Self::Item>> {
+            let mut demux = self.demux.lock().unwrap();
+            loop {
+                let choice = &mut demux.choices[self.index];
+                if let Some(text) = choice.pending.pop_front() {
+                    return Poll::Ready(Some(Ok(text)));
+                }
+                if choice.finished || demux.done {
+                    demux.wake_all();
+                    return Poll::Ready(None);
+                }
+                match demux.stream.poll_next_unpin(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => demux.dispatch(chunk),
+                    Poll::Ready(Some(Err(e))) => {
+                        demux.end();
+                        return Poll::Ready(Some(Err(e)));
+                    }
+                    Poll::Ready(None) => demux.end(),
+                    Poll::Pending => {
+                        // Whichever stream is polled next drives the connection and wakes this one.
+                        demux.choices[self.index].waker = Some(cx.waker().clone());
+                        return Poll::Pending;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Folds the chunks of a stream into the [ChatCompletion](super::ChatCompletion) they add up to.
+    ///
+    /// ```no_run
+    /// # async fn example(client: openai_rust2::Client, args: openai_rust2::chat::ChatArguments) -> openai_rust2::Result<()> {
+    /// use openai_rust2::chat::stream::ChatStreamAccumulator;
+    /// use openai_rust2::futures_util::StreamExt;
+    ///
+    /// let mut stream = client.create_chat_stream(args, None).await?;
+    /// let mut accumulator = ChatStreamAccumulator::new();
+    /// while let Some(chunk) = stream.next().await {
+    ///     let chunk = chunk?;
+    ///     print!("{}", chunk);
+    ///     accumulator.push(&chunk);
+    /// }
+    /// let completion = accumulator.finish();
+    /// println!("\n{:?}", completion.choices[0].finish_reason);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[derive(Debug, Clone, Default)]
+    pub struct ChatStreamAccumulator {
+        id: Option<String>,
+        created: u32,
+        model: Option<String>,
+        system_fingerprint: Option<String>,
+        service_tier: Option<String>,
+        /// By choice index.
+        choices: std::collections::BTreeMap<u32, AccumulatedChoice>,
+        usage: Option<Usage>,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct AccumulatedChoice {
+        role: Option<super::Role>,
+        content: Option<String>,
+        refusal: Option<String>,
+        /// By tool call index.
+        tool_calls: std::collections::BTreeMap<u32, super::ToolCall>,
+        finish_reason: Option<String>,
+        logprobs: Option<Logprobs>,
+    }
+
+    impl ChatStreamAccumulator {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub fn push(&mut self, chunk: &ChatCompletionChunk) {
+            self.id.get_or_insert_with(|| chunk.id.clone());
+            self.model.get_or_insert_with(|| chunk.model.clone());
+            if self.created == 0 {
+                self.created = chunk.created;
+            }
+            if chunk.system_fingerprint.is_some() {
+                self.system_fingerprint
+                    .clone_from(&chunk.system_fingerprint);
+            }
+            if chunk.service_tier.is_some() {
+                self.service_tier.clone_from(&chunk.service_tier);
+            }
+            if chunk.usage.is_some() {
+                self.usage.clone_from(&chunk.usage);
+            }
+            for choice in &chunk.choices {
+                let accumulated = self.choices.entry(choice.index).or_default();
+                if choice.delta.role.is_some() {
+                    accumulated.role.clone_from(&choice.delta.role);
+                }
+                if let Some(content) = &choice.delta.content {
+                    accumulated
+                        .content
+                        .get_or_insert_with(String::new)
+                        .push_str(content);
+                }
+                if let Some(refusal) = &choice.delta.refusal {
+                    accumulated
+                        .refusal
+                        .get_or_insert_with(String::new)
+                        .push_str(refusal);
+                }
+                for delta in choice.delta.tool_calls.iter().flatten() {
+                    let call = accumulated
+                        .tool_calls
+                        .entry(delta.index)
+                        .or_insert_with(|| super::ToolCall {
+                            id: String::new(),
+                            kind: super::function_type(),
+                            function: super::FunctionCall {
+                                name: String::new(),
+                                arguments: String::new(),
+                            },
+                        });
+                    if let Some(id) = &delta.id {
+                        call.id.clone_from(id);
+                    }
+                    if let Some(kind) = &delta.kind {
+                        call.kind.clone_from(kind);
+                    }
+                    if let Some(function) = &delta.function {
+                        if let Some(name) = &function.name {
+                            call.function.name.push_str(name);
                         }
-                        Err(e) => Poll::Ready(Some(Err(e.into()))),
-                    },
-                    None => Poll::Ready(None),
-                },
-                Poll::Pending => Poll::Pending,
+                        if let Some(arguments) = &function.arguments {
+                            call.function.arguments.push_str(arguments);
+                        }
+                    }
+                }
+                if choice.finish_reason.is_some() {
+                    accumulated.finish_reason.clone_from(&choice.finish_reason);
+                }
+                if let Some(logprobs) = &choice.logprobs {
+                    accumulated
+                        .logprobs
+                        .get_or_insert_with(Logprobs::default)
+                        .extend(logprobs);
+                }
+            }
+        }
+
+        /// The completion so far, ordered by choice index.
+        /// Choices that have not finished have an empty `finish_reason`,
+        /// and `usage` is all zero unless the final usage chunk was received.
+        pub fn finish(self) -> super::ChatCompletion {
+            super::ChatCompletion {
+                id: self.id,
+                created: self.created,
+                model: self.model,
+                object: Some("chat.completion".to_owned()),
+                choices: self
+                    .choices
+                    .into_iter()
+                    .map(|(index, choice)| super::Choice {
+                        index: Some(index),
+                        message: super::Message {
+                            role: choice.role.unwrap_or(super::Role::Assistant),
+                            content: choice.content.map(super::Content::Text),
+                            tool_calls: (!choice.tool_calls.is_empty())
+                                .then(|| choice.tool_calls.into_values().collect()),
+                            tool_call_id: None,
+                            refusal: choice.refusal,
+                        },
+                        finish_reason: choice.finish_reason.unwrap_or_default(),
+                        logprobs: choice.logprobs,
+                    })
+                    .collect(),
+                usage: self.usage.unwrap_or_default(),
+                system_fingerprint: self.system_fingerprint,
+                service_tier: self.service_tier,
             }
         }
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
@@ -244,18 +1216,516 @@ pub struct Choice {
     pub index: Option<u32>,
     pub message: Message,
     pub finish_reason: String,
+    #[serde(default)]
+    pub logprobs: Option<Logprobs>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Per-token log probabilities of a choice.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Logprobs {
+    #[serde(default)]
+    pub content: Option<Vec<TokenLogprob>>,
+    #[serde(default)]
+    pub refusal: Option<Vec<TokenLogprob>>,
+}
+
+impl Logprobs {
+    /// Append the tokens of a later chunk.
+    fn extend(&mut self, other: &Logprobs) {
+        for (tokens, more) in [
+            (&mut self.content, &other.content),
+            (&mut self.refusal, &other.refusal),
+        ] {
+            if let Some(more) = more {
+                tokens.get_or_insert_with(Vec::new).extend_from_slice(more);
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TokenLogprob {
+    pub token: String,
+    pub logprob: f32,
+    /// The UTF-8 bytes of the token, `None` if it has no textual representation.
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+    /// The most likely tokens at this position.
+    #[serde(default)]
+    pub top_logprobs: Vec<TopLogprob>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TopLogprob {
+    pub token: String,
+    pub logprob: f32,
+    #[serde(default)]
+    pub bytes: Option<Vec<u8>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Message {
-    pub role: String,
-    pub content: String,
+    pub role: Role,
+    /// `None` for assistant messages that only call tools.
+    #[serde(default)]
+    pub content: Option<Content>,
+    /// The calls an assistant message makes. Send the message back unchanged in the next request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// The call a `tool` message answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    /// Why the model declined to answer, instead of `content`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refusal: Option<String>,
+}
+
+impl Message {
+    pub fn new(role: Role, content: impl Into<Content>) -> Message {
+        Message {
+            role,
+            content: Some(content.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn system(content: impl Into<Content>) -> Message {
+        Message::new(Role::System, content)
+    }
+
+    pub fn developer(content: impl Into<Content>) -> Message {
+        Message::new(Role::Developer, content)
+    }
+
+    pub fn user(content: impl Into<Content>) -> Message {
+        Message::new(Role::User, content)
+    }
+
+    pub fn assistant(content: impl Into<Content>) -> Message {
+        Message::new(Role::Assistant, content)
+    }
+
+    /// The `tool` message answering the call with id `tool_call_id`.
+    pub fn tool(tool_call_id: impl Into<String>, content: impl Into<Content>) -> Message {
+        Message {
+            tool_call_id: Some(tool_call_id.into()),
+            ..Message::new(Role::Tool, content)
+        }
+    }
+
+    /// The `tool` message answering `call` with `content`.
+    pub fn tool_result(call: &ToolCall, content: impl Into<Content>) -> Message {
+        Message::tool(&call.id, content)
+    }
+}
+
+/// A tool for [ChatArguments::tools].
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Tool {
+    Function(FunctionTool),
+    /// Only supported by the Responses API.
+    OpenAI(OpenAITool),
+}
+
+impl Tool {
+    /// A function described by a JSON Schema for its arguments.
+    ///
+    /// ```
+    /// use openai_rust2::chat::Tool;
+    ///
+    /// let weather = Tool::function(
+    ///     "get_weather",
+    ///     "The current weather in a city.",
+    ///     serde_json::json!({
+    ///         "type": "object",
+    ///         "properties": {"city": {"type": "string"}},
+    ///         "required": ["city"],
+    ///     }),
+    /// );
+    /// ```
+    pub fn function(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Tool {
+        Tool::Function(FunctionTool {
+            function: FunctionDefinition {
+                name: name.into(),
+                description: Some(description.into()),
+                parameters: Some(parameters),
+                strict: None,
+            },
+        })
+    }
+}
+
+#[cfg(feature = "schemars")]
+impl Tool {
+    /// A function whose arguments are a `T`, strict unless `T` contains a map, see [strict_schema].
+    /// Read them with [ToolCall::parse_arguments].
+    pub fn typed<T: schemars::JsonSchema>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Tool {
+        let (parameters, strict) = schema::typed_schema::<T>();
+        Tool::Function(FunctionTool {
+            function: FunctionDefinition {
+                name: name.into(),
+                description: Some(description.into()),
+                parameters: Some(parameters),
+                strict: Some(strict),
+            },
+        })
+    }
+}
+
+/// Serialized with `"type": "function"`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename = "function")]
+pub struct FunctionTool {
+    pub function: FunctionDefinition,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FunctionDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// A JSON Schema object. `None` for functions without arguments.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parameters: Option<serde_json::Value>,
+    /// Make the model follow the schema exactly. Requires a schema the server supports in strict mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
+/// Whether and which tools the model must call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolChoice {
+    None,
+    Auto,
+    /// Call at least one tool.
+    Required,
+    /// Call the function with this name.
+    Function(String),
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ToolChoice::None => serializer.serialize_str("none"),
+            ToolChoice::Auto => serializer.serialize_str("auto"),
+            ToolChoice::Required => serializer.serialize_str("required"),
+            ToolChoice::Function(name) => serde_json::json!({
+                "type": "function",
+                "function": {"name": name},
+            })
+            .serialize(serializer),
+        }
+    }
+}
+
+/// A call of a [FunctionTool] made by the model.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "function_type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+impl ToolCall {
+    /// Deserialize the arguments. Empty arguments are read as `{}`.
+    pub fn parse_arguments<T: serde::de::DeserializeOwned>(&self) -> crate::Result<T> {
+        let arguments = match self.function.arguments.trim() {
+            "" => "{}",
+            arguments => arguments,
+        };
+        serde_json::from_str(arguments).map_err(|e| crate::Error::decode(e, arguments.as_bytes()))
+    }
+}
+
+fn function_type() -> String {
+    "function".to_owned()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FunctionCall {
+    pub name: String,
+    /// The arguments as JSON text. Models can produce invalid JSON, so parse with care.
+    pub arguments: String,
 }
 
+/// The content of a [Message]: plain text, or a list of parts for multimodal input.
+///
+/// Text is sent as a plain string, as before parts were supported.
+///
+/// ```no_run
+/// use openai_rust2::chat::{ContentPart, ImageDetail, Message};
+///
+/// let screenshot = std::fs::read("screenshot.png")?;
+/// let message = Message::user(vec![
+///     ContentPart::text("What is wrong on this page?"),
+///     ContentPart::image_bytes(&screenshot)
+///         .expect("not an image")
+///         .with_detail(ImageDetail::High),
+/// ]);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Content {
+    /// The text of plain text content, `None` for parts.
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Content::Text(text) => Some(text),
+            Content::Parts(_) => None,
+        }
+    }
+}
+
+/// The text, with the text parts of multimodal content joined.
+impl std::fmt::Display for Content {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Content::Text(text) => write!(f, "{}", text),
+            Content::Parts(parts) => parts.iter().try_for_each(|part| match part {
+                ContentPart::Text { text } => write!(f, "{}", text),
+                _ => Ok(()),
+            }),
+        }
+    }
+}
+
+impl From<String> for Content {
+    fn from(text: String) -> Self {
+        Content::Text(text)
+    }
+}
+
+impl From<&str> for Content {
+    fn from(text: &str) -> Self {
+        Content::Text(text.to_owned())
+    }
+}
+
+impl From<Vec<ContentPart>> for Content {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Content::Parts(parts)
+    }
+}
+
+/// One part of multimodal [Content]. Which parts a model accepts depends on the model.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+    InputAudio { input_audio: InputAudio },
+    File { file: FileContent },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImageUrl {
+    /// A web URL or a base64 `data:` URL.
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ImageDetail>,
+}
+
+/// The resolution the model looks at an image in. Higher detail costs more tokens.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageDetail {
+    Auto,
+    Low,
+    High,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InputAudio {
+    /// Base64-encoded audio.
+    pub data: String,
+    /// E.g. `wav` or `mp3`.
+    pub format: String,
+}
+
+/// A document, either uploaded beforehand or sent inline.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct FileContent {
+    /// The id of an uploaded file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_id: Option<String>,
+    /// A base64 `data:` URL.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_data: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filename: Option<String>,
+}
+
+impl ContentPart {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    /// An image at a web URL or a `data:` URL.
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: None,
+            },
+        }
+    }
+
+    /// An image sent inline as a `data:` URL.
+    /// `None` if `bytes` are not a PNG, JPEG, GIF or WebP image.
+    pub fn image_bytes(bytes: &[u8]) -> Option<Self> {
+        let mime = sniff_mime(bytes).filter(|mime| mime.starts_with("image/"))?;
+        Some(Self::image_url(data_url(mime, bytes)))
+    }
+
+    /// Read an image file and send it inline, see [image_bytes](Self::image_bytes).
+    pub fn image_file(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        Self::image_bytes(&std::fs::read(path)?).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("{} is not a PNG, JPEG, GIF or WebP image", path.display()),
+            )
+        })
+    }
+
+    /// Set the [ImageDetail] of an image part. Other parts are returned unchanged.
+    pub fn with_detail(mut self, detail: ImageDetail) -> Self {
+        if let ContentPart::ImageUrl { image_url } = &mut self {
+            image_url.detail = Some(detail);
+        }
+        self
+    }
+
+    /// Audio in `format`, e.g. `wav` or `mp3`.
+    pub fn input_audio(bytes: &[u8], format: impl Into<String>) -> Self {
+        ContentPart::InputAudio {
+            input_audio: InputAudio {
+                data: BASE64_STANDARD.encode(bytes),
+                format: format.into(),
+            },
+        }
+    }
+
+    /// A file uploaded beforehand.
+    pub fn file_id(file_id: impl Into<String>) -> Self {
+        ContentPart::File {
+            file: FileContent {
+                file_id: Some(file_id.into()),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// A document sent inline as a `data:` URL, e.g. a PDF.
+    /// The MIME type is sniffed from `bytes`, `application/octet-stream` if unknown.
+    pub fn file_bytes(filename: impl Into<String>, bytes: &[u8]) -> Self {
+        let mime = sniff_mime(bytes).unwrap_or("application/octet-stream");
+        ContentPart::File {
+            file: FileContent {
+                file_data: Some(data_url(mime, bytes)),
+                filename: Some(filename.into()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// A base64 `data:` URL.
+pub fn data_url(mime: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime, BASE64_STANDARD.encode(bytes))
+}
+
+/// The MIME type of common image, document and audio formats, from their leading bytes.
+pub fn sniff_mime(bytes: &[u8]) -> Option<&'static str> {
+    let mime = match bytes {
+        [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n', ..] => "image/png",
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => "audio/wav",
+        [b'%', b'P', b'D', b'F', b'-', ..] => "application/pdf",
+        [b'I', b'D', b'3', ..] | [0xFF, 0xFB | 0xF3 | 0xF2, ..] => "audio/mpeg",
+        _ => return None,
+    };
+    Some(mime)
+}
+
+/// The author of a [Message] or [ResponsesMessage], serialized in lowercase.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub enum Role {
     System,
-    Assistant,
+    /// Replaces `system` for OpenAI reasoning models.
+    Developer,
+    #[default]
     User,
+    Assistant,
+    Tool,
+    /// A role this crate does not know, sent as is.
+    Other(String),
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::System => "system",
+            Role::Developer => "developer",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::Other(role) => role,
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl From<&str> for Role {
+    fn from(role: &str) -> Self {
+        match role {
+            "system" => Role::System,
+            "developer" => Role::Developer,
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "tool" => Role::Tool,
+            _ => Role::Other(role.to_owned()),
+        }
+    }
+}
+
+impl From<String> for Role {
+    fn from(role: String) -> Self {
+        Role::from(role.as_str())
+    }
+}
+
+impl Serialize for Role {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Role::from)
+    }
 }
 
 // =============================================================================
@@ -421,12 +1891,12 @@ impl GrokTool {
 ///
 /// # Example
 /// ```rust,no_run
-/// use openai_rust2::chat::{ResponsesArguments, ResponsesMessage, GrokTool};
+/// use openai_rust2::chat::{ResponsesArguments, ResponsesMessage, GrokTool, Role};
 ///
 /// let args = ResponsesArguments::new(
 ///     "grok-4-1-fast-reasoning",
 ///     vec![ResponsesMessage {
-///         role: "user".to_string(),
+///         role: Role::User,
 ///         content: "What is the current price of Bitcoin?".to_string(),
 ///     }],
 /// ).with_tools(vec![GrokTool::web_search()]);
@@ -477,7 +1947,7 @@ impl ResponsesArguments {
 /// Message format for the Responses API input array.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ResponsesMessage {
-    pub role: String,
+    pub role: Role,
     pub content: String,
 }
 
@@ -539,7 +2009,7 @@ pub struct ResponsesOutputItem {
     #[serde(rename = "type")]
     pub item_type: String,
     #[serde(default)]
-    pub role: Option<String>,
+    pub role: Option<Role>,
     #[serde(default)]
     pub content: Option<Vec<ResponsesContent>>,
 }
@@ -605,7 +2075,7 @@ pub struct UserLocation {
 ///
 /// # Example
 /// ```rust,no_run
-/// use openai_rust2::chat::OpenAITool;
+/// use openai_rust2::chat::{OpenAITool, UserLocation};
 ///
 /// // Web search with geographic filtering
 /// let web_search = OpenAITool::web_search()
@@ -707,13 +2177,13 @@ impl OpenAITool {
 ///
 /// # Example
 /// ```rust,no_run
-/// use openai_rust2::chat::{OpenAIResponsesArguments, OpenAITool};
+/// use openai_rust2::chat::{OpenAIResponsesArguments, OpenAITool, ResponsesMessage, Role};
 ///
 /// let args = OpenAIResponsesArguments::new(
 ///     "gpt-5",
-///     vec![{
-///         "role": "user",
-///         "content": "What's the latest news about AI?"
+///     vec![ResponsesMessage {
+///         role: Role::User,
+///         content: "What's the latest news about AI?".to_string(),
 ///     }],
 /// ).with_tools(vec![OpenAITool::web_search()]);
 /// ```
@@ -0,0 +1,112 @@
+//! Tool parameter schemas generated from Rust types with `schemars`.
+use schemars::JsonSchema;
+use serde_json::{Map, Value};
+
+/// The JSON Schema of `T`, adjusted for [strict](super::FunctionDefinition::strict) function calling:
+/// every object lists all its properties as required and allows no others, `Option` fields
+/// accept `null`, and keywords strict mode rejects are dropped.
+///
+/// Strict mode cannot express maps such as `HashMap<String, T>`, whose keys are not known up front.
+/// Their `additionalProperties` are kept, so the schema is not valid in strict mode and
+/// [Tool::typed](super::Tool::typed) and [ResponseFormat::typed](super::ResponseFormat::typed)
+/// turn strict mode off for it.
+///
+/// ```
+/// use openai_rust2::chat::strict_schema;
+///
+/// #[derive(schemars::JsonSchema, serde::Deserialize)]
+/// struct Weather {
+///     /// The city name.
+///     city: String,
+///     days: Option<u8>,
+/// }
+///
+/// let schema = strict_schema::<Weather>();
+/// assert_eq!(schema["required"], serde_json::json!(["city", "days"]));
+/// assert_eq!(schema["additionalProperties"], false);
+/// ```
+pub fn strict_schema<T: JsonSchema>() -> Value {
+    typed_schema::<T>().0
+}
+
+/// The [strict_schema] of `T`, and whether strict mode can use it.
+pub(crate) fn typed_schema<T: JsonSchema>() -> (Value, bool) {
+    let mut schema = schemars::SchemaGenerator::default()
+        .into_root_schema_for::<T>()
+        .to_value();
+    if let Value::Object(root) = &mut schema {
+        root.remove("$schema");
+        root.remove("title");
+    }
+    let strict = make_strict(&mut schema);
+    (schema, strict)
+}
+
+/// Formats strict mode accepts, others are dropped.
+const FORMATS: &[&str] = &[
+    "date-time",
+    "time",
+    "date",
+    "duration",
+    "email",
+    "hostname",
+    "ipv4",
+    "ipv6",
+    "uuid",
+];
+
+/// Returns false if the schema contains a map, which strict mode cannot express.
+fn make_strict(schema: &mut Value) -> bool {
+    let Value::Object(schema) = schema else {
+        return true;
+    };
+    schema.remove("default");
+    if schema
+        .get("format")
+        .and_then(Value::as_str)
+        .is_some_and(|format| !FORMATS.contains(&format))
+    {
+        schema.remove("format");
+    }
+    // Strict mode supports anyOf but not oneOf, which schemars uses for enums with data.
+    if let Some(one_of) = schema.remove("oneOf") {
+        schema.insert("anyOf".to_owned(), one_of);
+    }
+    // Maps keep their `additionalProperties`, replacing them would only allow `{}`.
+    let map = schema
+        .get("additionalProperties")
+        .is_some_and(|extra| *extra != Value::Bool(false));
+    let mut strict = !map;
+    if let Some(Value::Object(properties)) = schema.get_mut("properties") {
+        for property in properties.values_mut() {
+            strict &= make_strict(property);
+        }
+        let required = properties.keys().cloned().map(Value::String).collect();
+        schema.insert("required".to_owned(), Value::Array(required));
+        if !map {
+            schema.insert("additionalProperties".to_owned(), Value::Bool(false));
+        }
+    } else if schema.get("type").and_then(Value::as_str) == Some("object") && !map {
+        schema.insert("properties".to_owned(), Value::Object(Map::new()));
+        schema.insert("required".to_owned(), Value::Array(Vec::new()));
+        schema.insert("additionalProperties".to_owned(), Value::Bool(false));
+    }
+    for key in ["items", "additionalProperties"] {
+        if let Some(nested) = schema.get_mut(key) {
+            strict &= make_strict(nested);
+        }
+    }
+    for key in ["anyOf", "allOf", "prefixItems"] {
+        if let Some(Value::Array(nested)) = schema.get_mut(key) {
+            for nested in nested {
+                strict &= make_strict(nested);
+            }
+        }
+    }
+    if let Some(Value::Object(defs)) = schema.get_mut("$defs") {
+        for def in defs.values_mut() {
+            strict &= make_strict(def);
+        }
+    }
+    strict
+}
@@ -0,0 +1,495 @@
+//! Runs the function-calling loop with local Rust functions, see [ToolRunner].
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures_util::future::{join_all, BoxFuture};
+use futures_util::FutureExt;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    ChatArguments, ChatCompletion, FunctionDefinition, FunctionTool, Message, Tool, ToolCall,
+};
+use crate::{Client, Result};
+
+type Handler =
+    Arc<dyn Fn(Value) -> BoxFuture<'static, std::result::Result<Value, String>> + Send + Sync>;
+
+/// A named async function the model can call, with a JSON Schema for its arguments.
+#[derive(Clone)]
+pub struct LocalTool {
+    name: String,
+    description: String,
+    parameters: Value,
+    strict: Option<bool>,
+    timeout: Option<Duration>,
+    handler: Handler,
+}
+
+impl LocalTool {
+    /// `handler` gets the arguments once they passed validation against `parameters`.
+    /// Its result is sent to the model as JSON, strings as they are. An error is sent
+    /// to the model as well, so it can correct itself.
+    pub fn new<F, Fut, T, E>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: std::fmt::Display,
+    {
+        let handler = Arc::new(move |args| {
+            handler(args)
+                .map(|res| match res {
+                    Ok(value) => serde_json::to_value(value).map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                })
+                .boxed()
+        });
+        Self {
+            name: name.into(),
+            description: description.into(),
+            parameters,
+            strict: None,
+            timeout: None,
+            handler,
+        }
+    }
+
+    /// A tool whose arguments are deserialized into an `A`, with the schema generated from it,
+    /// see [strict_schema](super::strict_schema). Strict unless `A` contains a map.
+    #[cfg(feature = "schemars")]
+    pub fn typed<A, F, Fut, T, E>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        handler: F,
+    ) -> Self
+    where
+        A: schemars::JsonSchema + serde::de::DeserializeOwned,
+        F: Fn(A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<T, E>> + Send + 'static,
+        T: Serialize,
+        E: std::fmt::Display,
+    {
+        let (parameters, strict) = super::schema::typed_schema::<A>();
+        let tool = Self::new(name, description, parameters, move |args: Value| {
+            let call = serde_json::from_value(args)
+                .map(&handler)
+                .map_err(|e| e.to_string());
+            async move {
+                match call {
+                    Ok(call) => call.await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                }
+            }
+        });
+        tool.with_strict(strict)
+    }
+
+    /// See [FunctionDefinition::strict](super::FunctionDefinition::strict).
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = Some(strict);
+        self
+    }
+
+    /// Overrides [ToolRunner::with_timeout] for this tool.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl std::fmt::Debug for LocalTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalTool")
+            .field("name", &self.name)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Drives [Client::create_chat] until the model answers without calling a tool,
+/// executing the calls it makes with registered [LocalTool]s.
+///
+/// ```no_run
+/// # async fn example(client: openai_rust2::Client, args: openai_rust2::chat::ChatArguments) -> openai_rust2::Result<()> {
+/// use std::time::Duration;
+/// use openai_rust2::chat::{LocalTool, ToolRunner};
+/// use serde_json::{json, Value};
+///
+/// let runner = ToolRunner::new()
+///     .with_tool(LocalTool::new(
+///         "get_weather",
+///         "The current weather in a city.",
+///         json!({"type": "object", "properties": {"city": {"type": "string"}}, "required": ["city"]}),
+///         |args: Value| async move { Ok::<_, String>(format!("Sunny in {}", args["city"])) },
+///     ))
+///     .with_max_iterations(5)
+///     .with_timeout(Duration::from_secs(10));
+/// let run = runner.run(&client, args).await?;
+/// println!("{} after {} steps", run.completion, run.steps.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ToolRunner {
+    tools: HashMap<String, LocalTool>,
+    max_iterations: usize,
+    parallel: bool,
+    timeout: Option<Duration>,
+}
+
+impl Default for ToolRunner {
+    fn default() -> Self {
+        Self {
+            tools: HashMap::new(),
+            max_iterations: 10,
+            parallel: true,
+            timeout: None,
+        }
+    }
+}
+
+impl ToolRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `tool`, replacing one with the same name.
+    pub fn with_tool(mut self, tool: LocalTool) -> Self {
+        self.tools.insert(tool.name.clone(), tool);
+        self
+    }
+
+    /// The most requests to make. Defaults to 10.
+    pub fn with_max_iterations(mut self, max_iterations: usize) -> Self {
+        self.max_iterations = max_iterations;
+        self
+    }
+
+    /// Whether the calls of one response run concurrently. Defaults to true.
+    pub fn with_parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Timeout for each call of a tool without its own [LocalTool::with_timeout].
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run the conversation in `args`, offering the registered tools in addition to
+    /// any already in `args.tools`.
+    pub async fn run(&self, client: &Client, mut args: ChatArguments) -> Result<ToolRun> {
+        let mut names: Vec<_> = self.tools.keys().collect();
+        names.sort();
+        args.tools
+            .get_or_insert_with(Vec::new)
+            .extend(names.into_iter().map(|name| {
+                let tool = &self.tools[name];
+                Tool::Function(FunctionTool {
+                    function: FunctionDefinition {
+                        name: tool.name.clone(),
+                        description: Some(tool.description.clone()),
+                        parameters: Some(tool.parameters.clone()),
+                        strict: tool.strict,
+                    },
+                })
+            }));
+
+        let mut steps = Vec::new();
+        loop {
+            let completion = client.create_chat(args.clone(), None).await?;
+            let message = completion.choices.first().map(|c| c.message.clone());
+            let calls = message
+                .as_ref()
+                .and_then(|m| m.tool_calls.clone())
+                .unwrap_or_default();
+            args.messages.extend(message);
+            if calls.is_empty() || steps.len() + 1 >= self.max_iterations {
+                return Ok(ToolRun {
+                    exhausted: !calls.is_empty(),
+                    completion,
+                    messages: args.messages,
+                    steps,
+                });
+            }
+
+            let records = if self.parallel {
+                join_all(calls.into_iter().map(|call| self.execute(call))).await
+            } else {
+                let mut records = Vec::new();
+                for call in calls {
+                    records.push(self.execute(call).await);
+                }
+                records
+            };
+            args.messages.extend(
+                records
+                    .iter()
+                    .map(|r| Message::tool_result(&r.call, r.outcome.content())),
+            );
+            steps.push(ToolStep {
+                completion,
+                calls: records,
+            });
+        }
+    }
+
+    async fn execute(&self, call: ToolCall) -> ToolCallRecord {
+        let started = Instant::now();
+        let outcome = match self.tools.get(&call.function.name) {
+            None => ToolOutcome::UnknownTool,
+            Some(tool) => match parse_arguments(&call.function.arguments, &tool.parameters) {
+                Err(e) => ToolOutcome::InvalidArguments(e),
+                Ok(args) => {
+                    let result = (tool.handler)(args);
+                    match tool.timeout.or(self.timeout) {
+                        Some(timeout) => match tokio::time::timeout(timeout, result).await {
+                            Ok(result) => result.into(),
+                            Err(_) => ToolOutcome::TimedOut(timeout),
+                        },
+                        None => result.await.into(),
+                    }
+                }
+            },
+        };
+        ToolCallRecord {
+            call,
+            outcome,
+            duration: started.elapsed(),
+        }
+    }
+}
+
+/// Models send empty arguments for functions without parameters.
+fn parse_arguments(arguments: &str, schema: &Value) -> std::result::Result<Value, String> {
+    let args = match arguments.trim() {
+        "" => Value::Object(Default::default()),
+        arguments => serde_json::from_str(arguments).map_err(|e| e.to_string())?,
+    };
+    validate(schema, &args, "$")?;
+    Ok(args)
+}
+
+/// The result of [ToolRunner::run].
+#[derive(Debug, Clone)]
+pub struct ToolRun {
+    /// The last response: the answer, or unanswered tool calls if [exhausted](Self::exhausted).
+    pub completion: ChatCompletion,
+    /// The conversation including every assistant and tool message.
+    pub messages: Vec<Message>,
+    /// Every response that called tools, with the results.
+    pub steps: Vec<ToolStep>,
+    /// The model was still calling tools when the iteration limit was reached.
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolStep {
+    pub completion: ChatCompletion,
+    pub calls: Vec<ToolCallRecord>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ToolCallRecord {
+    pub call: ToolCall,
+    pub outcome: ToolOutcome,
+    pub duration: Duration,
+}
+
+/// What happened to a tool call. Every outcome is reported back to the model.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolOutcome {
+    Ok(Value),
+    /// The tool returned an error.
+    Failed(String),
+    /// The arguments were not JSON or did not match the schema.
+    InvalidArguments(String),
+    TimedOut(Duration),
+    UnknownTool,
+}
+
+impl From<std::result::Result<Value, String>> for ToolOutcome {
+    fn from(res: std::result::Result<Value, String>) -> Self {
+        match res {
+            Ok(value) => ToolOutcome::Ok(value),
+            Err(e) => ToolOutcome::Failed(e),
+        }
+    }
+}
+
+impl ToolOutcome {
+    /// The content of the `tool` message.
+    fn content(&self) -> String {
+        match self {
+            ToolOutcome::Ok(Value::String(text)) => text.clone(),
+            ToolOutcome::Ok(value) => value.to_string(),
+            ToolOutcome::Failed(e) => format!("error: {}", e),
+            ToolOutcome::InvalidArguments(e) => format!("error: invalid arguments: {}", e),
+            ToolOutcome::TimedOut(timeout) => format!("error: timed out after {:?}", timeout),
+            ToolOutcome::UnknownTool => "error: no such tool".to_owned(),
+        }
+    }
+}
+
+/// Check `value` against the subset of JSON Schema used for tool parameters:
+/// `type`, `enum`, `const`, `properties`, `required`, `additionalProperties`, `items`,
+/// `anyOf`, the length and range keywords, and local `$ref`s such as `#/$defs/Point`.
+/// Other keywords are ignored.
+pub(crate) fn validate(
+    schema: &Value,
+    value: &Value,
+    path: &str,
+) -> std::result::Result<(), String> {
+    validate_in(schema, schema, value, path)
+}
+
+/// [validate] with `$ref`s resolved against `root`.
+fn validate_in(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &str,
+) -> std::result::Result<(), String> {
+    let validate =
+        |schema: &Value, value: &Value, path: &str| validate_in(root, schema, value, path);
+    let Some(schema) = resolve(root, schema).as_object() else {
+        return Ok(());
+    };
+    let fail = |message: String| Err(format!("{}: {}", path, message));
+
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(t) => vec![t],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            return fail(format!("expected {}", types.join(" or ")));
+        }
+    }
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            return fail(format!("expected one of {}", Value::Array(allowed.clone())));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            return fail(format!("expected {}", expected));
+        }
+    }
+    if let Some(Value::Array(options)) = schema.get("anyOf") {
+        if !options.iter().any(|s| validate(s, value, path).is_ok()) {
+            return fail("matches none of anyOf".to_owned());
+        }
+    }
+    let bound = |name: &str| schema.get(name).and_then(Value::as_f64);
+
+    match value {
+        Value::Object(object) => {
+            for name in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let Some(name) = name.as_str() else { continue };
+                if !object.contains_key(name) {
+                    return fail(format!("missing property {}", name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, property) in object {
+                match properties.and_then(|p| p.get(name)) {
+                    Some(property_schema) => {
+                        validate(property_schema, property, &format!("{}.{}", path, name))?
+                    }
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            return fail(format!("unexpected property {}", name))
+                        }
+                        Some(extra) => validate(extra, property, &format!("{}.{}", path, name))?,
+                        None => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{}[{}]", path, i))?;
+                }
+            }
+            check_range(items.len() as f64, bound("minItems"), bound("maxItems"))
+                .or_else(|e| fail(format!("{} items", e)))?;
+        }
+        Value::String(text) => {
+            check_range(
+                text.chars().count() as f64,
+                bound("minLength"),
+                bound("maxLength"),
+            )
+            .or_else(|e| fail(format!("length {}", e)))?;
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            check_range(number, bound("minimum"), bound("maximum")).or_else(fail)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Follow `schema`'s local `$ref`, if any. Unresolvable and cyclic references accept anything.
+fn resolve<'a>(root: &'a Value, mut schema: &'a Value) -> &'a Value {
+    for _ in 0..32 {
+        let Some(reference) = schema.get("$ref").and_then(Value::as_str) else {
+            return schema;
+        };
+        match reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            Some(target) => schema = target,
+            None => return &Value::Null,
+        }
+    }
+    &Value::Null
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => true,
+    }
+}
+
+fn check_range(n: f64, min: Option<f64>, max: Option<f64>) -> std::result::Result<(), String> {
+    if let Some(min) = min.filter(|min| n < *min) {
+        return Err(format!("{} is less than {}", n, min));
+    }
+    if let Some(max) = max.filter(|max| n > *max) {
+        return Err(format!("{} is more than {}", n, max));
+    }
+    Ok(())
+}
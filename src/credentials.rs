@@ -0,0 +1,288 @@
+//! Pluggable credentials, asked for before every request.
+//!
+//! A [Client](crate::Client) built with [with_api_key](crate::ClientBuilder::with_api_key) uses a
+//! [StaticKey]. Keys that rotate or expire can be supplied through
+//! [with_credentials](crate::ClientBuilder::with_credentials) instead:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//! use openai_rust2::credentials::{Credential, EnvVar, FileKey, RefreshingProvider};
+//!
+//! // Re-read whenever the file changes, e.g. a mounted Kubernetes secret.
+//! let from_file = FileKey::new("/var/run/secrets/openai/api-key");
+//!
+//! // Read the environment variable on every request.
+//! let from_env = EnvVar::new("OPENAI_API_KEY");
+//!
+//! // Fetch short-lived tokens and cache them until shortly before they expire.
+//! let refreshing = RefreshingProvider::new(|| async {
+//!     let token = String::from("fetched from your identity provider");
+//!     Ok(Credential::bearer(token).with_expires_in(Duration::from_secs(3600)))
+//! });
+//!
+//! let client = openai_rust2::Client::builder()
+//!     .with_credentials(refreshing)
+//!     .build()?;
+//! # Ok::<(), openai_rust2::Error>(())
+//! ```
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+
+use crate::{Error, Result};
+
+/// The header a [Credential] is sent in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthHeader {
+    /// `Authorization: Bearer <secret>`
+    Bearer,
+    /// `api-key: <secret>`, as used by Azure OpenAI.
+    ApiKey,
+    /// Any other header name, e.g. `x-api-key`.
+    Custom(String),
+}
+
+/// A secret and where to send it.
+#[derive(Clone)]
+pub struct Credential {
+    pub secret: String,
+    pub header: AuthHeader,
+    /// When the credential stops being valid. Used by [RefreshingProvider] to decide when to refresh.
+    pub expires_at: Option<Instant>,
+}
+
+impl Credential {
+    pub fn new(secret: impl Into<String>, header: AuthHeader) -> Self {
+        Self {
+            secret: secret.into(),
+            header,
+            expires_at: None,
+        }
+    }
+
+    pub fn bearer(secret: impl Into<String>) -> Self {
+        Self::new(secret, AuthHeader::Bearer)
+    }
+
+    pub fn api_key(secret: impl Into<String>) -> Self {
+        Self::new(secret, AuthHeader::ApiKey)
+    }
+
+    pub fn with_expires_at(mut self, expires_at: Instant) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    pub fn with_expires_in(self, expires_in: Duration) -> Self {
+        self.with_expires_at(Instant::now() + expires_in)
+    }
+
+    pub(crate) fn apply(&self, req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.header {
+            AuthHeader::Bearer => req.bearer_auth(&self.secret),
+            AuthHeader::ApiKey => req.header("api-key", &self.secret),
+            AuthHeader::Custom(name) => req.header(name.as_str(), &self.secret),
+        }
+    }
+}
+
+impl std::fmt::Debug for Credential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("secret", &"<redacted>")
+            .field("header", &self.header)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Supplies the [Credential] for each request.
+pub trait CredentialProvider: Send + Sync {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>>;
+}
+
+/// A fixed credential.
+#[derive(Debug, Clone)]
+pub struct StaticKey(Credential);
+
+impl StaticKey {
+    /// A key sent as a bearer token.
+    pub fn new(key: impl Into<String>) -> Self {
+        Self(Credential::bearer(key))
+    }
+
+    pub fn with_header(mut self, header: AuthHeader) -> Self {
+        self.0.header = header;
+        self
+    }
+}
+
+impl From<Credential> for StaticKey {
+    fn from(credential: Credential) -> Self {
+        Self(credential)
+    }
+}
+
+impl CredentialProvider for StaticKey {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        futures_util::future::ready(Ok(self.0.clone())).boxed()
+    }
+}
+
+/// Reads the key from an environment variable on every request.
+#[derive(Debug, Clone)]
+pub struct EnvVar {
+    name: String,
+    header: AuthHeader,
+}
+
+impl EnvVar {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            header: AuthHeader::Bearer,
+        }
+    }
+
+    pub fn with_header(mut self, header: AuthHeader) -> Self {
+        self.header = header;
+        self
+    }
+}
+
+impl CredentialProvider for EnvVar {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        let credential = std::env::var(&self.name)
+            .map(|key| Credential::new(key, self.header.clone()))
+            .map_err(|e| Error::Credential(format!("{}: {}", self.name, e).into()));
+        futures_util::future::ready(credential).boxed()
+    }
+}
+
+/// Reads the key from a file and reads it again whenever the file's modification time changes.
+/// Surrounding whitespace is trimmed. The file is checked on the blocking thread pool.
+pub struct FileKey {
+    path: PathBuf,
+    header: AuthHeader,
+    cached: Arc<Mutex<Option<(SystemTime, String)>>>,
+}
+
+impl FileKey {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            header: AuthHeader::Bearer,
+            cached: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn with_header(mut self, header: AuthHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    fn read(
+        path: &std::path::Path,
+        cached: &Mutex<Option<(SystemTime, String)>>,
+    ) -> std::io::Result<String> {
+        let modified = std::fs::metadata(path)?.modified()?;
+        let mut cached = cached.lock().unwrap();
+        match &*cached {
+            Some((at, key)) if *at == modified => Ok(key.clone()),
+            _ => {
+                let key = std::fs::read_to_string(path)?.trim().to_owned();
+                *cached = Some((modified, key.clone()));
+                Ok(key)
+            }
+        }
+    }
+}
+
+impl CredentialProvider for FileKey {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        let (path, cached) = (self.path.clone(), self.cached.clone());
+        async move {
+            let key = tokio::task::spawn_blocking(move || Self::read(&path, &cached))
+                .await
+                .map_err(std::io::Error::other)
+                .and_then(|res| res)
+                .map_err(|e| Error::Credential(format!("{}: {}", self.path.display(), e).into()))?;
+            Ok(Credential::new(key, self.header.clone()))
+        }
+        .boxed()
+    }
+}
+
+impl std::fmt::Debug for FileKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileKey")
+            .field("path", &self.path)
+            .field("header", &self.header)
+            .finish_non_exhaustive()
+    }
+}
+
+type RefreshFn = Box<dyn Fn() -> BoxFuture<'static, Result<Credential>> + Send + Sync>;
+
+/// Fetches credentials with an async function and caches them until shortly before they expire.
+/// Credentials without an expiry are cached forever.
+///
+/// Concurrent requests wait for a single refresh instead of each starting their own.
+pub struct RefreshingProvider {
+    refresh: RefreshFn,
+    cached: tokio::sync::Mutex<Option<Credential>>,
+    margin: Duration,
+}
+
+impl RefreshingProvider {
+    pub fn new<F, Fut>(refresh: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Credential>> + Send + 'static,
+    {
+        Self {
+            refresh: Box::new(move || refresh().boxed()),
+            cached: tokio::sync::Mutex::new(None),
+            margin: Duration::from_secs(60),
+        }
+    }
+
+    /// How long before expiry the credential is refreshed. Defaults to 60 seconds.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+}
+
+impl CredentialProvider for RefreshingProvider {
+    fn credential(&self) -> BoxFuture<'_, Result<Credential>> {
+        async move {
+            let mut cached = self.cached.lock().await;
+            let fresh = |c: &Credential| {
+                c.expires_at
+                    .is_none_or(|at| Instant::now() + self.margin < at)
+            };
+            match &*cached {
+                Some(credential) if fresh(credential) => Ok(credential.clone()),
+                _ => {
+                    let credential = (self.refresh)().await?;
+                    *cached = Some(credential.clone());
+                    Ok(credential)
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+impl std::fmt::Debug for RefreshingProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RefreshingProvider")
+            .field("margin", &self.margin)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,227 @@
+//! Errors returned by every [Client](crate::Client) call.
+//!
+//! ```no_run
+//! # async fn example(client: openai_rust2::Client, args: openai_rust2::chat::ChatArguments) {
+//! use openai_rust2::Error;
+//!
+//! match client.create_chat(args, None).await {
+//!     Ok(res) => println!("{}", res),
+//!     Err(Error::Api(e)) if e.status.as_u16() == 429 => println!("rate limited: {}", e),
+//!     Err(e) => println!("{}", e),
+//! }
+//! # }
+//! ```
+use serde::{Deserialize, Deserializer};
+
+/// Shorthand for results whose error type is [Error].
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// The error type of this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The request could not be sent, or the connection broke while reading the response.
+    Transport(reqwest::Error),
+    /// The server answered with a success status but the body could not be deserialized.
+    Decode {
+        source: serde_json::Error,
+        /// The raw response body (or the part of it that failed to parse).
+        body: String,
+    },
+    /// The request arguments could not be serialized.
+    Encode(serde_json::Error),
+    /// The server answered with a non-2xx status.
+    Api(Box<ApiError>),
+    /// The [ClientBuilder](crate::ClientBuilder) was given an invalid configuration.
+    Config(String),
+    /// The [CredentialProvider](crate::credentials::CredentialProvider) failed to supply a credential.
+    Credential(Box<dyn std::error::Error + Send + Sync>),
+    /// A [Cassette](crate::cassette::Cassette) could not be read or written,
+    /// or has no recorded interaction for a request.
+    Cassette(String),
+    /// The [RateLimiter](crate::rate_limit::RateLimiter) budget of `model` is exhausted
+    /// and it was configured to [fail fast](crate::rate_limit::OnLimit::FailFast).
+    RateLimited {
+        model: String,
+        /// How long until the budget has refilled enough for the call.
+        retry_after: std::time::Duration,
+    },
+    /// A streamed response missed one of its [StreamTimeouts](crate::chat::stream::StreamTimeouts).
+    StreamTimeout {
+        deadline: crate::chat::stream::StreamDeadline,
+        /// Time since the call was made.
+        elapsed: std::time::Duration,
+    },
+}
+
+impl Error {
+    /// The HTTP status of the response, if one was received.
+    pub fn status(&self) -> Option<reqwest::StatusCode> {
+        match self {
+            Error::Transport(e) => e.status(),
+            Error::Api(e) => Some(e.status),
+            _ => None,
+        }
+    }
+
+    /// The `x-request-id` of the failed request, if the server sent one.
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            Error::Api(e) => e.request_id.as_deref(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn decode(source: serde_json::Error, body: &[u8]) -> Error {
+        Error::Decode {
+            source,
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "request failed: {}", e),
+            Error::Decode { source, .. } => write!(f, "failed to decode response: {}", source),
+            Error::Encode(e) => write!(f, "failed to encode request: {}", e),
+            Error::Api(e) => write!(f, "{}", e),
+            Error::Config(message) => write!(f, "invalid client configuration: {}", message),
+            Error::Credential(e) => write!(f, "failed to get credential: {}", e),
+            Error::Cassette(message) => write!(f, "cassette: {}", message),
+            Error::RateLimited { model, retry_after } => write!(
+                f,
+                "client-side rate limit for {} reached, retry in {:?}",
+                model, retry_after
+            ),
+            Error::StreamTimeout { deadline, elapsed } => write!(
+                f,
+                "stream missed its {:?} deadline after {:?}",
+                deadline, elapsed
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Decode { source, .. } => Some(source),
+            Error::Encode(e) => Some(e),
+            Error::Api(e) => Some(e.as_ref()),
+            Error::Config(_) => None,
+            Error::Credential(e) => Some(e.as_ref()),
+            Error::Cassette(_) => None,
+            Error::RateLimited { .. } => None,
+            Error::StreamTimeout { .. } => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+impl From<ApiError> for Error {
+    fn from(e: ApiError) -> Self {
+        Error::Api(Box::new(e))
+    }
+}
+
+/// A non-2xx response from the API.
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    /// The HTTP status code.
+    pub status: reqwest::StatusCode,
+    /// The parsed `error` object, if the body contained one.
+    pub error: Option<ApiErrorObject>,
+    /// The value of the `x-request-id` header, useful when contacting support.
+    pub request_id: Option<String>,
+    /// The raw response body.
+    pub body: String,
+}
+
+impl ApiError {
+    /// The error message sent by the server.
+    pub fn message(&self) -> Option<&str> {
+        self.error.as_ref().map(|e| e.message.as_str())
+    }
+
+    /// The error `type`, e.g. `invalid_request_error`.
+    pub fn error_type(&self) -> Option<&str> {
+        self.error.as_ref().and_then(|e| e.error_type.as_deref())
+    }
+
+    /// The error `code`, e.g. `invalid_api_key` or `rate_limit_exceeded`.
+    pub fn code(&self) -> Option<&str> {
+        self.error.as_ref().and_then(|e| e.code.as_deref())
+    }
+
+    /// The request parameter the error relates to, if any.
+    pub fn param(&self) -> Option<&str> {
+        self.error.as_ref().and_then(|e| e.param.as_deref())
+    }
+
+    pub(crate) fn new(
+        status: reqwest::StatusCode,
+        headers: &reqwest::header::HeaderMap,
+        body: &[u8],
+    ) -> ApiError {
+        let error = serde_json::from_slice::<ErrorEnvelope>(body)
+            .ok()
+            .map(|e| e.error);
+        ApiError {
+            status,
+            error,
+            request_id: headers
+                .get("x-request-id")
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned),
+            body: String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+}
+
+impl std::fmt::Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "API error ({}): {}", self.status, message),
+            None => write!(f, "API error ({}): {}", self.status, self.body),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+/// The `error` object of an API error response.
+///
+/// See <https://platform.openai.com/docs/guides/error-codes>.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ApiErrorObject {
+    #[serde(default)]
+    pub message: String,
+    #[serde(default, rename = "type")]
+    pub error_type: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub code: Option<String>,
+    #[serde(default, deserialize_with = "string_or_number")]
+    pub param: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ErrorEnvelope {
+    error: ApiErrorObject,
+}
+
+/// Some OpenAI-compatible servers send numeric codes.
+fn string_or_number<'de, D: Deserializer<'de>>(d: D) -> Result<Option<String>, D::Error> {
+    Ok(match Option::<serde_json::Value>::deserialize(d)? {
+        Some(serde_json::Value::String(s)) => Some(s),
+        Some(serde_json::Value::Null) | None => None,
+        Some(v) => Some(v.to_string()),
+    })
+}
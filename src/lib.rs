@@ -1,107 +1,171 @@
 pub extern crate futures_util;
-use anyhow::{anyhow, Result};
+use futures_util::{StreamExt, TryStreamExt};
 use lazy_static::lazy_static;
+use rate_limit::EstimateTokens;
+use serde::{de::DeserializeOwned, Serialize};
 
 lazy_static! {
     static ref DEFAULT_BASE_URL: reqwest::Url =
-        reqwest::Url::parse("https://api.openai.com/v1/models").unwrap();
+        reqwest::Url::parse("https://api.openai.com/v1").unwrap();
 }
 
+/// A client for the OpenAI API and compatible servers.
+///
+/// With the `tracing` feature, every call is recorded in an `openai` span with the fields
+/// `endpoint`, `model`, `status`, `request_id`, `latency_ms`, `prompt_tokens`,
+/// `completion_tokens` and `total_tokens`. Spans of [create_chat_stream](Client::create_chat_stream)
+/// also record `time_to_first_chunk_ms` and `chunks`, and close when the stream ends.
 pub struct Client {
     req_client: reqwest::Client,
-    key: String,
+    credentials: std::sync::Arc<dyn credentials::CredentialProvider>,
     base_url: reqwest::Url,
+    default_headers: reqwest::header::HeaderMap,
+    timeout: Option<std::time::Duration>,
+    stream_timeouts: chat::stream::StreamTimeouts,
+    retry_policy: retry::RetryPolicy,
+    azure: Option<azure::AzureConfig>,
+    middleware: Vec<std::sync::Arc<dyn middleware::Middleware>>,
+    rate_limiter: Option<rate_limit::RateLimiter>,
 }
 
+pub mod azure;
+mod builder;
+pub mod cassette;
 pub mod chat;
 pub mod completions;
+pub mod credentials;
 pub mod edits;
 pub mod embeddings;
+pub mod error;
 pub mod images;
+pub mod meta;
+pub mod middleware;
 pub mod models;
+pub mod rate_limit;
+pub mod retry;
+pub mod sse;
+mod telemetry;
+
+pub use builder::ClientBuilder;
+pub use error::{Error, Result};
 
 impl Client {
+    /// Configure a client with fallible construction, see [ClientBuilder].
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
     pub fn new(api_key: &str) -> Client {
-        let req_client = reqwest::ClientBuilder::new().build().unwrap();
-        Client {
-            req_client,
-            key: api_key.to_owned(),
-            base_url: DEFAULT_BASE_URL.clone(),
-        }
+        Client::builder()
+            .with_api_key(api_key)
+            .build()
+            .expect("failed to build the HTTP client")
     }
 
     pub fn new_with_client(api_key: &str, req_client: reqwest::Client) -> Client {
-        Client {
-            req_client,
-            key: api_key.to_owned(),
-            base_url: DEFAULT_BASE_URL.clone(),
-        }
+        Client::builder()
+            .with_api_key(api_key)
+            .with_http_client(req_client)
+            .build()
+            .expect("failed to build the client")
     }
 
+    /// Panics if `base_url` is invalid, use [Client::builder] to handle that case.
     pub fn new_with_base_url(api_key: &str, base_url: &str) -> Client {
-        let req_client = reqwest::ClientBuilder::new().build().unwrap();
-        let base_url = reqwest::Url::parse(base_url).unwrap();
-        Client {
-            req_client,
-            key: api_key.to_owned(),
-            base_url,
-        }
+        Client::builder()
+            .with_api_key(api_key)
+            .with_base_url(base_url)
+            .build()
+            .unwrap()
     }
 
+    /// Panics if `base_url` is invalid, use [Client::builder] to handle that case.
     pub fn new_with_client_and_base_url(
         api_key: &str,
         req_client: reqwest::Client,
         base_url: &str,
     ) -> Client {
-        Client {
-            req_client,
-            key: api_key.to_owned(),
-            base_url: reqwest::Url::parse(base_url).unwrap(),
-        }
+        Client::builder()
+            .with_api_key(api_key)
+            .with_http_client(req_client)
+            .with_base_url(base_url)
+            .build()
+            .unwrap()
     }
 
-    pub async fn list_models(
-        &self,
-        opt_url_path: Option<String>,
-    ) -> Result<Vec<models::Model>, anyhow::Error> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/models")));
+    /// Replace the [RetryPolicy](retry::RetryPolicy) used for every request.
+    /// Use [RetryPolicy::none](retry::RetryPolicy::none) to disable retries.
+    pub fn with_retry_policy(mut self, policy: retry::RetryPolicy) -> Client {
+        self.retry_policy = policy;
+        self
+    }
 
-        let res = self
-            .req_client
-            .get(url)
-            .bearer_auth(&self.key)
-            .send()
-            .await?;
+    pub async fn list_models(&self, opt_url_path: Option<String>) -> Result<Vec<models::Model>> {
+        let url = self.url("models", None, opt_url_path);
 
-        if res.status() == 200 {
-            Ok(res.json::<models::ListModelsResponse>().await?.data)
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        let span = telemetry::CallSpan::new("models", None);
+        Ok(span
+            .run(self.get_json::<models::ListModelsResponse>("models", url))
+            .await?
+            .data)
     }
 
     pub async fn create_chat(
         &self,
         args: chat::ChatArguments,
         opt_url_path: Option<String>,
-    ) -> Result<chat::ChatCompletion, anyhow::Error> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/chat/completions")));
+    ) -> Result<chat::ChatCompletion> {
+        let url = self.url("chat/completions", Some(&args.model), opt_url_path);
 
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
-            .await?;
+        // The API rejects stream_options without stream, e.g. when arguments are reused.
+        let mut args = args;
+        args.stream_options = None;
+
+        let span = telemetry::CallSpan::new("chat/completions", Some(&args.model));
+        let tokens = args.estimated_tokens();
+        self.limited(
+            &args.model,
+            tokens,
+            span.run(self.post_json("chat/completions", url, &args)),
+        )
+        .await
+    }
 
-        if res.status() == 200 {
-            Ok(res.json().await?)
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+    /// Ask for a JSON answer matching the schema of `T` and deserialize it,
+    /// see [ResponseFormat::typed](chat::ResponseFormat::typed). Replaces `args.response_format`.
+    ///
+    /// ```no_run
+    /// # async fn example(client: openai_rust2::Client, args: openai_rust2::chat::ChatArguments) -> openai_rust2::Result<()> {
+    /// use openai_rust2::chat::Parsed;
+    ///
+    /// #[derive(serde::Deserialize, schemars::JsonSchema)]
+    /// struct Event {
+    ///     name: String,
+    ///     date: String,
+    /// }
+    ///
+    /// match client.create_chat_parsed::<Event>(args, None).await?.parsed {
+    ///     Parsed::Value(event) => println!("{} on {}", event.name, event.date),
+    ///     Parsed::Refusal(refusal) => println!("refused: {}", refusal),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "schemars")]
+    pub async fn create_chat_parsed<T>(
+        &self,
+        mut args: chat::ChatArguments,
+        opt_url_path: Option<String>,
+    ) -> Result<chat::ParsedCompletion<T>>
+    where
+        T: serde::de::DeserializeOwned + schemars::JsonSchema,
+    {
+        args.response_format = Some(chat::ResponseFormat::typed::<T>());
+        let completion = self.create_chat(args, opt_url_path).await?;
+        Ok(chat::ParsedCompletion {
+            parsed: completion.parse()?,
+            completion,
+        })
     }
 
     pub async fn create_chat_stream(
@@ -109,27 +173,39 @@ impl Client {
         args: chat::ChatArguments,
         opt_url_path: Option<String>,
     ) -> Result<chat::stream::ChatCompletionChunkStream> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/chat/completions")));
+        let url = self.url("chat/completions", Some(&args.model), opt_url_path);
 
         let mut args = args;
         args.stream = Some(true);
 
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
+        let span = telemetry::CallSpan::new("chat/completions", Some(&args.model));
+        let tokens = args.estimated_tokens();
+        let permit = self.permit(&args.model, tokens).await?;
+        // Deadlines count from here, time spent waiting for the rate limiter does not.
+        let started = std::time::Instant::now();
+        let timeouts = self.stream_timeouts;
+        let request = self.post_stream("chat/completions", url, &args);
+        let res = span
+            .run(async {
+                let Some((timeout, deadline)) = timeouts.response_deadline() else {
+                    return request.await;
+                };
+                tokio::time::timeout(timeout, request)
+                    .await
+                    .map_err(|_| Error::StreamTimeout {
+                        deadline,
+                        elapsed: started.elapsed(),
+                    })?
+            })
             .await?;
 
-        if res.status() == 200 {
-            Ok(chat::stream::ChatCompletionChunkStream::new(Box::pin(
-                res.bytes_stream(),
-            )))
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        Ok(chat::stream::ChatCompletionChunkStream::new(
+            res.body.into_stream(),
+            span,
+            timeouts,
+            started,
+            permit,
+        ))
     }
 
     pub async fn create_completion(
@@ -137,22 +213,16 @@ impl Client {
         args: completions::CompletionArguments,
         opt_url_path: Option<String>,
     ) -> Result<completions::CompletionResponse> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/completions")));
-
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
-            .await?;
-
-        if res.status() == 200 {
-            Ok(res.json().await?)
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        let url = self.url("completions", Some(&args.model), opt_url_path);
+
+        let span = telemetry::CallSpan::new("completions", Some(&args.model));
+        let tokens = args.estimated_tokens();
+        self.limited(
+            &args.model,
+            tokens,
+            span.run(self.post_json("completions", url, &args)),
+        )
+        .await
     }
 
     pub async fn create_embeddings(
@@ -160,22 +230,16 @@ impl Client {
         args: embeddings::EmbeddingsArguments,
         opt_url_path: Option<String>,
     ) -> Result<embeddings::EmbeddingsResponse> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/embeddings")));
-
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
-            .await?;
-
-        if res.status() == 200 {
-            Ok(res.json().await?)
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        let url = self.url("embeddings", Some(&args.model), opt_url_path);
+
+        let span = telemetry::CallSpan::new("embeddings", Some(&args.model));
+        let tokens = args.estimated_tokens();
+        self.limited(
+            &args.model,
+            tokens,
+            span.run(self.post_json("embeddings", url, &args)),
+        )
+        .await
     }
 
     pub async fn create_image_old(
@@ -183,31 +247,29 @@ impl Client {
         args: images::ImageArguments,
         opt_url_path: Option<String>,
     ) -> Result<Vec<String>> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/images/generations")));
-
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
-            .await?;
-
-        if res.status() == 200 {
-            Ok(res
-                .json::<images::ImageResponse>()
-                .await?
-                .data
-                .iter()
-                .map(|o| match o {
-                    images::ImageObject::Url(s) => s.to_string(),
-                    images::ImageObject::Base64JSON(s) => s.to_string(),
-                })
-                .collect())
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        // dall-e-2 is what the API defaults to when no model is given.
+        let model = args.model.as_deref().unwrap_or("dall-e-2");
+        let url = self.url("images/generations", Some(model), opt_url_path);
+
+        let span = telemetry::CallSpan::new("images/generations", Some(model));
+        Ok(self
+            .limited(
+                model,
+                0,
+                span.run(self.post_json::<_, images::ImageResponse>(
+                    "images/generations",
+                    url,
+                    &args,
+                )),
+            )
+            .await?
+            .data
+            .iter()
+            .map(|o| match o {
+                images::ImageObject::Url(s) => s.to_string(),
+                images::ImageObject::Base64JSON(s) => s.to_string(),
+            })
+            .collect())
     }
 
     pub async fn create_image(
@@ -215,9 +277,6 @@ impl Client {
         args: images::ImageArguments,
         opt_url_path: Option<String>,
     ) -> Result<Vec<String>> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/images/generations")));
-
         let image_args = images::ImageArguments {
             prompt: args.prompt,
             model: Some("gpt-image-1".to_string()),
@@ -228,28 +287,32 @@ impl Client {
             user: None,
         };
 
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&image_args)
-            .send()
-            .await?;
-
-        if res.status() == 200 {
-            Ok(res
-                .json::<images::ImageResponse>()
-                .await?
-                .data
-                .iter()
-                .map(|o| match o {
-                    images::ImageObject::Url(s) => s.to_string(),
-                    images::ImageObject::Base64JSON(s) => s.to_string(),
-                })
-                .collect())
-        } else {
-            Err(anyhow!(res.text().await?))
-        }
+        let url = self.url(
+            "images/generations",
+            image_args.model.as_deref(),
+            opt_url_path,
+        );
+
+        let model = image_args.model.as_deref().unwrap_or_default();
+        let span = telemetry::CallSpan::new("images/generations", Some(model));
+        Ok(self
+            .limited(
+                model,
+                0,
+                span.run(self.post_json::<_, images::ImageResponse>(
+                    "images/generations",
+                    url,
+                    &image_args,
+                )),
+            )
+            .await?
+            .data
+            .iter()
+            .map(|o| match o {
+                images::ImageObject::Url(s) => s.to_string(),
+                images::ImageObject::Base64JSON(s) => s.to_string(),
+            })
+            .collect())
     }
 
     /// Create a response using xAI's Responses API with agentic tool calling.
@@ -263,7 +326,7 @@ impl Client {
     ///
     /// # Example
     /// ```rust,no_run
-    /// use openai_rust2::chat::{ResponsesArguments, ResponsesMessage, GrokTool};
+    /// use openai_rust2::chat::{ResponsesArguments, ResponsesMessage, GrokTool, Role};
     /// use openai_rust2::Client;
     ///
     /// async fn example() -> anyhow::Result<()> {
@@ -271,7 +334,7 @@ impl Client {
     ///     let args = ResponsesArguments::new(
     ///         "grok-4-1-fast-reasoning",
     ///         vec![ResponsesMessage {
-    ///             role: "user".to_string(),
+    ///             role: Role::User,
     ///             content: "What is the current Bitcoin price?".to_string(),
     ///         }],
     ///     ).with_tools(vec![GrokTool::web_search()]);
@@ -285,23 +348,17 @@ impl Client {
         &self,
         args: chat::ResponsesArguments,
         opt_url_path: Option<String>,
-    ) -> Result<chat::ResponsesCompletion, anyhow::Error> {
-        let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/responses")));
+    ) -> Result<chat::ResponsesCompletion> {
+        let url = self.url("responses", None, opt_url_path);
 
-        let res = self
-            .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
-            .send()
-            .await?;
-
-        if res.status() == 200 {
-            Ok(res.json().await?)
-        } else {
-            Err(anyhow!(res.text().await?))
+        let model = args.model.clone();
+        let span = telemetry::CallSpan::new("responses", Some(&model));
+        let mut args = args;
+        if let Some(azure) = &self.azure {
+            args.model = azure.deployment(&args.model).to_owned();
         }
+        self.limited(&model, 0, span.run(self.post_json("responses", url, &args)))
+            .await
     }
 
     /// Create a response using OpenAI's Responses API with agentic tool calling.
@@ -317,7 +374,7 @@ impl Client {
     ///
     /// # Example
     /// ```rust,no_run
-    /// use openai_rust2::chat::{OpenAIResponsesArguments, ResponsesMessage, OpenAITool};
+    /// use openai_rust2::chat::{OpenAIResponsesArguments, ResponsesMessage, OpenAITool, Role};
     /// use openai_rust2::Client;
     ///
     /// async fn example() -> anyhow::Result<()> {
@@ -325,7 +382,7 @@ impl Client {
     ///     let args = OpenAIResponsesArguments::new(
     ///         "gpt-5",
     ///         vec![ResponsesMessage {
-    ///             role: "user".to_string(),
+    ///             role: Role::User,
     ///             content: "What are the latest developments in AI?".to_string(),
     ///         }],
     ///     ).with_tools(vec![OpenAITool::web_search()]);
@@ -339,22 +396,178 @@ impl Client {
         &self,
         args: chat::OpenAIResponsesArguments,
         opt_url_path: Option<String>,
-    ) -> Result<chat::ResponsesCompletion, anyhow::Error> {
+    ) -> Result<chat::ResponsesCompletion> {
+        let url = self.url("responses", None, opt_url_path);
+
+        let model = args.model.clone();
+        let span = telemetry::CallSpan::new("responses", Some(&model));
+        let mut args = args;
+        if let Some(azure) = &self.azure {
+            args.model = azure.deployment(&args.model).to_owned();
+        }
+        self.limited(&model, 0, span.run(self.post_json("responses", url, &args)))
+            .await
+    }
+
+    /// The URL of `endpoint` (e.g. `chat/completions`) relative to the base URL.
+    ///
+    /// A base URL without a path, like `http://localhost:11434`, gets the `/v1` prefix.
+    /// Any other base path is kept, so `https://gateway.corp/openai/v1` becomes
+    /// `https://gateway.corp/openai/v1/chat/completions`.
+    /// `opt_url_path` replaces the whole path, as it always has.
+    ///
+    /// In Azure mode, endpoints that are served per deployment pass the `model` they are called with.
+    fn url(
+        &self,
+        endpoint: &str,
+        model: Option<&str>,
+        opt_url_path: Option<String>,
+    ) -> reqwest::Url {
         let mut url = self.base_url.clone();
-        url.set_path(&opt_url_path.unwrap_or_else(|| String::from("/v1/responses")));
+        let base_path = url.path().trim_end_matches('/');
+        let path = match (opt_url_path, &self.azure) {
+            (Some(path), _) => path,
+            (None, Some(azure)) => azure.path(base_path, endpoint, model),
+            (None, None) if base_path.is_empty() => format!("/v1/{}", endpoint),
+            (None, None) => format!("{}/{}", base_path, endpoint),
+        };
+        url.set_path(&path);
+        if let Some(azure) = &self.azure {
+            url.query_pairs_mut()
+                .append_pair("api-version", &azure.api_version);
+        }
+        url
+    }
 
-        let res = self
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        url: reqwest::Url,
+    ) -> Result<T> {
+        let req = middleware::Request::new(endpoint, reqwest::Method::GET, url);
+        decode_json(self.execute(req).await?).await
+    }
+
+    async fn post_json<A: Serialize, T: DeserializeOwned>(
+        &self,
+        endpoint: &'static str,
+        url: reqwest::Url,
+        args: &A,
+    ) -> Result<T> {
+        let mut req = middleware::Request::new(endpoint, reqwest::Method::POST, url);
+        req.set_json(args)?;
+        decode_json(self.execute(req).await?).await
+    }
+
+    async fn post_stream<A: Serialize>(
+        &self,
+        endpoint: &'static str,
+        url: reqwest::Url,
+        args: &A,
+    ) -> Result<middleware::Response> {
+        let mut req = middleware::Request::new(endpoint, reqwest::Method::POST, url);
+        req.set_json(args)?;
+        req.stream = true;
+        self.execute(req).await
+    }
+
+    /// Run `call` once the [RateLimiter](rate_limit::RateLimiter), if any, admits one request
+    /// and `tokens` for `model`.
+    async fn limited<T: telemetry::ReportsUsage>(
+        &self,
+        model: &str,
+        tokens: u32,
+        call: impl std::future::Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let permit = self.permit(model, tokens).await?;
+        let res = call.await;
+        if let (Some(permit), Ok(value)) = (permit, &res) {
+            permit.reconcile(value);
+        }
+        res
+    }
+
+    /// Admission by the [RateLimiter](rate_limit::RateLimiter), `None` without one.
+    async fn permit(&self, model: &str, tokens: u32) -> Result<Option<rate_limit::Permit>> {
+        match &self.rate_limiter {
+            Some(limiter) => limiter.acquire(model, tokens).await.map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Run a request through the middleware chain, retrying according to the
+    /// [RetryPolicy](retry::RetryPolicy). Any non-2xx response that is not retried becomes an [Error::Api].
+    async fn execute(&self, mut req: middleware::Request) -> Result<middleware::Response> {
+        let mut headers = self.default_headers.clone();
+        headers.extend(req.headers);
+        req.headers = headers;
+
+        let started = std::time::Instant::now();
+        let mut attempt = 1;
+        loop {
+            let retries_left = self.retry_policy.retries_left(attempt);
+            let res = middleware::Next::new(self, &self.middleware)
+                .run(req.clone())
+                .await;
+            let delay = match res {
+                Ok(res) if res.status.is_success() => {
+                    telemetry::record_response(&res);
+                    meta::record(res.status, &res.headers, started.elapsed());
+                    return Ok(res);
+                }
+                Ok(res) if retries_left && self.retry_policy.should_retry_status(res.status) => {
+                    self.retry_policy.delay(attempt, Some(&res.headers))
+                }
+                Ok(res) => {
+                    let body = res.body.bytes().await?;
+                    return Err(error::ApiError::new(res.status, &res.headers, &body).into());
+                }
+                Err(e) if retries_left && self.retry_policy.should_retry_error(&e) => {
+                    self.retry_policy.delay(attempt, None)
+                }
+                Err(e) => return Err(e),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// The end of the middleware chain: add the credential and send the request.
+    async fn transport(&self, req: middleware::Request) -> Result<middleware::Response> {
+        let mut builder = self
             .req_client
-            .post(url)
-            .bearer_auth(&self.key)
-            .json(&args)
+            .request(req.method, req.url)
+            .headers(req.headers);
+        if let Some(body) = req.body {
+            builder = builder.body(body);
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        let res = self
+            .credentials
+            .credential()
+            .await?
+            .apply(builder)
             .send()
             .await?;
 
-        if res.status() == 200 {
-            Ok(res.json().await?)
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = if req.stream && status.is_success() {
+            middleware::Body::Stream(res.bytes_stream().map_err(Error::from).boxed())
         } else {
-            Err(anyhow!(res.text().await?))
-        }
+            middleware::Body::Bytes(res.bytes().await?)
+        };
+        Ok(middleware::Response {
+            status,
+            headers,
+            body,
+        })
     }
 }
+
+async fn decode_json<T: DeserializeOwned>(res: middleware::Response) -> Result<T> {
+    let body = res.body.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| Error::decode(e, &body))
+}
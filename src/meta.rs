@@ -0,0 +1,146 @@
+//! Response metadata: rate-limit state, request id, status, latency and headers.
+//!
+//! Wrap any [Client](crate::Client) call in [WithMeta::capture] to get the typed result
+//! together with the [ResponseMeta] of its final HTTP response.
+//!
+//! ```no_run
+//! # async fn example(client: openai_rust2::Client, args: openai_rust2::chat::ChatArguments) -> openai_rust2::Result<()> {
+//! use openai_rust2::meta::WithMeta;
+//!
+//! let res = WithMeta::capture(client.create_chat(args, None)).await?;
+//! if let Some(meta) = &res.meta {
+//!     println!("{} (request {:?})", res.body, meta.request_id);
+//!     if let Some(remaining) = meta.rate_limit.remaining_tokens {
+//!         println!("{} tokens left this minute", remaining);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::retry::{parse_duration, secs_f64};
+use crate::Result;
+
+/// A response body together with its [ResponseMeta].
+#[derive(Debug, Clone)]
+pub struct WithMeta<T> {
+    pub body: T,
+    /// `None` if no response was seen, see [capture](Self::capture).
+    pub meta: Option<ResponseMeta>,
+}
+
+impl<T> WithMeta<T> {
+    /// Run `call` and attach the metadata of the last response it received.
+    ///
+    /// Calls that made several requests report the last one. The metadata is `None` if `call`
+    /// succeeded without making a request, or made it in a task it spawned, where the
+    /// capture is not visible.
+    pub async fn capture<F>(call: F) -> Result<WithMeta<T>>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        CAPTURED
+            .scope(RefCell::new(None), async {
+                let body = call.await?;
+                let meta = CAPTURED.with(|captured| captured.borrow_mut().take());
+                Ok(WithMeta { body, meta })
+            })
+            .await
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> WithMeta<U> {
+        WithMeta {
+            body: f(self.body),
+            meta: self.meta,
+        }
+    }
+}
+
+impl<T> std::ops::Deref for WithMeta<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.body
+    }
+}
+
+/// Metadata of a successful HTTP response.
+#[derive(Debug, Clone)]
+pub struct ResponseMeta {
+    pub status: StatusCode,
+    /// The `x-request-id` header, useful when contacting support.
+    pub request_id: Option<String>,
+    /// Time spent by the server, from the `openai-processing-ms` header.
+    pub processing_time: Option<Duration>,
+    /// Time from sending the request until the response headers arrived, including retries.
+    pub latency: Duration,
+    pub rate_limit: RateLimitState,
+    /// All response headers.
+    pub headers: HeaderMap,
+}
+
+impl ResponseMeta {
+    pub(crate) fn new(status: StatusCode, headers: &HeaderMap, latency: Duration) -> Self {
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+        Self {
+            status,
+            request_id: header("x-request-id").map(str::to_owned),
+            processing_time: header("openai-processing-ms")
+                .and_then(|ms| ms.trim().parse::<f64>().ok())
+                .and_then(|ms| secs_f64(ms / 1000.0)),
+            latency,
+            rate_limit: RateLimitState::from_headers(headers),
+            headers: headers.clone(),
+        }
+    }
+}
+
+/// The quota state reported in the `x-ratelimit-*` headers.
+/// Fields are `None` when the server did not send the header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RateLimitState {
+    pub limit_requests: Option<u64>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    /// Time until the request budget is fully restored.
+    pub reset_requests: Option<Duration>,
+    /// Time until the token budget is fully restored.
+    pub reset_tokens: Option<Duration>,
+}
+
+impl RateLimitState {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| {
+            headers
+                .get(format!("x-ratelimit-{}", name))
+                .and_then(|v| v.to_str().ok())
+        };
+        let count = |name: &str| header(name).and_then(|v| v.trim().parse().ok());
+        Self {
+            limit_requests: count("limit-requests"),
+            limit_tokens: count("limit-tokens"),
+            remaining_requests: count("remaining-requests"),
+            remaining_tokens: count("remaining-tokens"),
+            reset_requests: header("reset-requests").and_then(parse_duration),
+            reset_tokens: header("reset-tokens").and_then(parse_duration),
+        }
+    }
+}
+
+tokio::task_local! {
+    static CAPTURED: RefCell<Option<ResponseMeta>>;
+}
+
+/// Hand the final response of a request to an enclosing [WithMeta::capture], if any.
+pub(crate) fn record(status: StatusCode, headers: &HeaderMap, latency: Duration) {
+    let _ = CAPTURED.try_with(|captured| {
+        *captured.borrow_mut() = Some(ResponseMeta::new(status, headers, latency));
+    });
+}
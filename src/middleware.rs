@@ -0,0 +1,201 @@
+//! Hooks between [Client](crate::Client) and the network.
+//!
+//! Middleware added with [ClientBuilder::with_middleware](crate::ClientBuilder::with_middleware)
+//! runs in the order it was added, for every attempt of every request.
+//! Each one sees the endpoint, the serialized [Request] and the raw [Response]
+//! (including streaming responses) and may modify or replace either.
+//! Calling [Next::run] passes the request on, not calling it short-circuits the request.
+//!
+//! Credentials are added after the last middleware, so they never show up in a [Request].
+//!
+//! ```
+//! use openai_rust2::middleware::{from_fn, Response};
+//!
+//! let client = openai_rust2::Client::builder()
+//!     // Tag every request.
+//!     .with_middleware(from_fn(|mut req, next| {
+//!         Box::pin(async move {
+//!             req.headers.insert("x-team", "search".parse().unwrap());
+//!             next.run(req).await
+//!         })
+//!     }))
+//!     // Answer model listings without touching the network.
+//!     .with_middleware(from_fn(|req, next| {
+//!         Box::pin(async move {
+//!             if req.endpoint == "models" {
+//!                 return Ok(Response::new(200, r#"{"data":[]}"#));
+//!             }
+//!             next.run(req).await
+//!         })
+//!     }))
+//!     .build()?;
+//! # Ok::<(), openai_rust2::Error>(())
+//! ```
+use std::sync::Arc;
+
+use bytes::Bytes;
+use futures_util::future::BoxFuture;
+use futures_util::stream::{BoxStream, StreamExt, TryStreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{Client, Error, Result};
+
+/// A request about to be sent.
+#[derive(Debug, Clone)]
+pub struct Request {
+    /// The endpoint relative to the API root, e.g. `chat/completions` or `models`.
+    pub endpoint: &'static str,
+    pub method: Method,
+    pub url: Url,
+    pub headers: HeaderMap,
+    /// The serialized JSON body.
+    pub body: Option<Bytes>,
+    /// Whether the response is streamed. Streamed responses carry a [Body::Stream].
+    pub stream: bool,
+}
+
+impl Request {
+    pub(crate) fn new(endpoint: &'static str, method: Method, url: Url) -> Self {
+        Self {
+            endpoint,
+            method,
+            url,
+            headers: HeaderMap::new(),
+            body: None,
+            stream: false,
+        }
+    }
+
+    /// Deserialize the body.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<Option<T>> {
+        self.body
+            .as_ref()
+            .map(|body| serde_json::from_slice(body).map_err(|e| Error::decode(e, body)))
+            .transpose()
+    }
+
+    /// Replace the body with `value` serialized as JSON.
+    pub fn set_json<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        let body = serde_json::to_vec(value).map_err(Error::Encode)?;
+        self.body = Some(body.into());
+        self.headers.insert(
+            reqwest::header::CONTENT_TYPE,
+            reqwest::header::HeaderValue::from_static("application/json"),
+        );
+        Ok(())
+    }
+}
+
+/// A response as received from the network, before it is decoded.
+#[derive(Debug)]
+pub struct Response {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Body,
+}
+
+impl Response {
+    /// A response with `status` and a complete body, e.g. to short-circuit a request.
+    ///
+    /// Panics if `status` is not a valid status code.
+    pub fn new(status: u16, body: impl Into<Bytes>) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("invalid status code"),
+            headers: HeaderMap::new(),
+            body: Body::Bytes(body.into()),
+        }
+    }
+}
+
+/// The body of a [Response].
+pub enum Body {
+    Bytes(Bytes),
+    Stream(BoxStream<'static, Result<Bytes>>),
+}
+
+impl Body {
+    /// Wait for the whole body.
+    pub async fn bytes(self) -> Result<Bytes> {
+        match self {
+            Body::Bytes(bytes) => Ok(bytes),
+            Body::Stream(stream) => Ok(stream.try_collect::<Vec<_>>().await?.concat().into()),
+        }
+    }
+
+    /// The body as a stream of chunks.
+    pub fn into_stream(self) -> BoxStream<'static, Result<Bytes>> {
+        match self {
+            Body::Bytes(bytes) => futures_util::stream::once(async { Ok(bytes) }).boxed(),
+            Body::Stream(stream) => stream,
+        }
+    }
+}
+
+impl std::fmt::Debug for Body {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Body::Stream(_) => f.write_str("Stream"),
+        }
+    }
+}
+
+/// A hook around every request, see the [module documentation](self).
+pub trait Middleware: Send + Sync {
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>>;
+}
+
+/// The rest of the chain after the current middleware.
+pub struct Next<'a> {
+    client: &'a Client,
+    rest: &'a [Arc<dyn Middleware>],
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(client: &'a Client, chain: &'a [Arc<dyn Middleware>]) -> Self {
+        Self {
+            client,
+            rest: chain,
+        }
+    }
+
+    /// Pass the request to the next middleware, or send it if this was the last one.
+    pub async fn run(self, req: Request) -> Result<Response> {
+        match self.rest.split_first() {
+            Some((first, rest)) => {
+                first
+                    .handle(
+                        req,
+                        Next {
+                            client: self.client,
+                            rest,
+                        },
+                    )
+                    .await
+            }
+            None => self.client.transport(req).await,
+        }
+    }
+}
+
+/// Build a [Middleware] from a closure.
+pub fn from_fn<F>(f: F) -> FromFn<F>
+where
+    F: for<'a> Fn(Request, Next<'a>) -> BoxFuture<'a, Result<Response>> + Send + Sync,
+{
+    FromFn(f)
+}
+
+/// A [Middleware] created by [from_fn].
+pub struct FromFn<F>(F);
+
+impl<F> Middleware for FromFn<F>
+where
+    F: for<'a> Fn(Request, Next<'a>) -> BoxFuture<'a, Result<Response>> + Send + Sync,
+{
+    fn handle<'a>(&'a self, req: Request, next: Next<'a>) -> BoxFuture<'a, Result<Response>> {
+        (self.0)(req, next)
+    }
+}
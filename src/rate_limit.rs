@@ -0,0 +1,378 @@
+//! Client-side request and token budgets per model.
+//!
+//! A [RateLimiter] added with [ClientBuilder::with_rate_limiter](crate::ClientBuilder::with_rate_limiter)
+//! enforces requests per minute and tokens per minute for each model, shared by every call on the
+//! [Client](crate::Client). Tokens are estimated before sending (about four characters per token,
+//! plus `max_tokens` for each requested choice) and corrected with the `usage` of the response.
+//! Streams are corrected when their usage chunk arrives, see
+//! [ChatArguments::with_stream_options](crate::chat::ChatArguments::with_stream_options);
+//! streams without one keep the estimate.
+//!
+//! When a budget is exhausted the call either waits for it to refill or fails with
+//! [Error::RateLimited], see [OnLimit]. Waiting calls are admitted by [Priority],
+//! so interactive traffic goes ahead of bulk jobs sharing the same client.
+//!
+//! ```no_run
+//! # async fn example(args: openai_rust2::chat::ChatArguments) -> openai_rust2::Result<()> {
+//! use openai_rust2::rate_limit::{Limits, Priority, RateLimiter};
+//!
+//! let client = openai_rust2::Client::builder()
+//!     .with_api_key("sk-...")
+//!     .with_rate_limiter(
+//!         RateLimiter::new()
+//!             .with_limits("gpt-4o", Limits::new().with_requests_per_minute(500).with_tokens_per_minute(30_000))
+//!             .with_default_limits(Limits::new().with_requests_per_minute(60)),
+//!     )
+//!     .build()?;
+//!
+//! // Calls made inside the scope wait behind interactive ones.
+//! Priority::Bulk.scope(client.create_chat(args, None)).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::telemetry::ReportsUsage;
+use crate::{chat, completions, embeddings, Error, Result};
+
+/// Budgets for one model. Unset budgets are unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl Limits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_requests_per_minute(mut self, requests: u32) -> Self {
+        self.requests_per_minute = Some(requests);
+        self
+    }
+
+    pub fn with_tokens_per_minute(mut self, tokens: u32) -> Self {
+        self.tokens_per_minute = Some(tokens);
+        self
+    }
+}
+
+/// What a call does when its model's budget is exhausted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnLimit {
+    /// Wait until the budget has refilled.
+    #[default]
+    Wait,
+    /// Fail immediately with [Error::RateLimited].
+    FailFast,
+}
+
+/// The order in which waiting calls are admitted.
+/// A call only proceeds once no call of a higher priority is waiting for the same model.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Background work, e.g. batch jobs.
+    Bulk,
+    #[default]
+    Normal,
+    /// Latency-sensitive work, e.g. a user waiting for an answer.
+    Interactive,
+}
+
+tokio::task_local! {
+    static PRIORITY: Priority;
+}
+
+impl Priority {
+    /// Run `future` with this priority for every call it makes.
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        PRIORITY.scope(self, future).await
+    }
+
+    /// The priority of the current task, [Priority::Normal] outside of a [scope](Self::scope).
+    pub fn current() -> Priority {
+        PRIORITY.try_with(|p| *p).unwrap_or_default()
+    }
+}
+
+/// Enforces [Limits] per model, see the [module documentation](self).
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    limits: HashMap<String, Limits>,
+    default_limits: Limits,
+    on_limit: OnLimit,
+    /// Shared with the [Permit]s of streams, which reconcile after the call has returned.
+    state: Arc<Mutex<HashMap<String, ModelState>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Budgets for `model`, matched against the `model` of each call.
+    pub fn with_limits(mut self, model: impl Into<String>, limits: Limits) -> Self {
+        self.limits.insert(model.into(), limits);
+        self
+    }
+
+    /// Budgets for models without their own [with_limits](Self::with_limits).
+    /// Each model still gets a separate budget. Defaults to unlimited.
+    pub fn with_default_limits(mut self, limits: Limits) -> Self {
+        self.default_limits = limits;
+        self
+    }
+
+    /// Defaults to [OnLimit::Wait].
+    pub fn with_on_limit(mut self, on_limit: OnLimit) -> Self {
+        self.on_limit = on_limit;
+        self
+    }
+
+    fn limits(&self, model: &str) -> Limits {
+        self.limits
+            .get(model)
+            .copied()
+            .unwrap_or(self.default_limits)
+    }
+
+    /// Take one request and `tokens` from the budget of `model`.
+    pub(crate) async fn acquire(&self, model: &str, tokens: u32) -> Result<Permit> {
+        let limits = self.limits(model);
+        if limits == Limits::default() {
+            return Ok(Permit {
+                state: self.state.clone(),
+                model: None,
+                taken: 0.0,
+            });
+        }
+        let priority = Priority::current();
+        let mut waiting: Option<Waiting> = None;
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let model_state = state
+                    .entry(model.to_owned())
+                    .or_insert_with(|| ModelState::new(limits));
+                model_state.refill();
+                let wait = model_state.wait_for(tokens);
+                let outranked = model_state.waiting_above(priority);
+                if wait.is_zero() && !outranked {
+                    let taken = model_state.take(tokens);
+                    if let Some(waiting) = &mut waiting {
+                        waiting.leave(model_state);
+                    }
+                    return Ok(Permit {
+                        state: self.state.clone(),
+                        model: Some(model.to_owned()),
+                        taken,
+                    });
+                }
+                if self.on_limit == OnLimit::FailFast {
+                    return Err(Error::RateLimited {
+                        model: model.to_owned(),
+                        retry_after: wait,
+                    });
+                }
+                if waiting.is_none() {
+                    model_state.waiting[priority as usize] += 1;
+                    waiting = Some(Waiting {
+                        limiter: self,
+                        model,
+                        priority,
+                        active: true,
+                    });
+                }
+                // Outranked calls re-check regularly, the budget they wait for may be taken first.
+                wait.max(Duration::from_millis(10))
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// A request admitted by the [RateLimiter]. Dropping it without reconciling keeps the estimate.
+pub(crate) struct Permit {
+    state: Arc<Mutex<HashMap<String, ModelState>>>,
+    /// `None` for models without limits.
+    model: Option<String>,
+    /// The tokens taken from the budget, the estimate capped at the per-minute limit.
+    taken: f64,
+}
+
+impl Permit {
+    /// Correct the estimate with the tokens the response reports.
+    pub(crate) fn reconcile<T: ReportsUsage>(self, res: &T) {
+        let (Some(model), Some(used)) = (self.model, res.tokens().and_then(|t| t.total)) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if let Some(model_state) = state.get_mut(&model) {
+            if model_state.limits.tokens_per_minute.is_some() {
+                model_state.tokens += self.taken - used as f64;
+            }
+        }
+    }
+}
+
+/// Keeps a waiting call counted until it is admitted or cancelled.
+struct Waiting<'a> {
+    limiter: &'a RateLimiter,
+    model: &'a str,
+    priority: Priority,
+    active: bool,
+}
+
+impl Waiting<'_> {
+    fn leave(&mut self, model_state: &mut ModelState) {
+        if std::mem::take(&mut self.active) {
+            model_state.waiting[self.priority as usize] -= 1;
+        }
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        if self.active {
+            let mut state = self.limiter.state.lock().unwrap();
+            if let Some(model_state) = state.get_mut(self.model) {
+                self.leave(model_state);
+            }
+        }
+    }
+}
+
+/// Token buckets that refill continuously up to one minute's budget.
+#[derive(Debug)]
+struct ModelState {
+    limits: Limits,
+    requests: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    /// Waiting calls per [Priority].
+    waiting: [usize; 3],
+}
+
+impl ModelState {
+    fn new(limits: Limits) -> Self {
+        Self {
+            limits,
+            requests: limits.requests_per_minute.unwrap_or(0) as f64,
+            tokens: limits.tokens_per_minute.unwrap_or(0) as f64,
+            refilled_at: Instant::now(),
+            waiting: [0; 3],
+        }
+    }
+
+    fn refill(&mut self) {
+        let minutes = self.refilled_at.elapsed().as_secs_f64() / 60.0;
+        self.refilled_at = Instant::now();
+        if let Some(rpm) = self.limits.requests_per_minute {
+            self.requests = (self.requests + minutes * rpm as f64).min(rpm as f64);
+        }
+        if let Some(tpm) = self.limits.tokens_per_minute {
+            self.tokens = (self.tokens + minutes * tpm as f64).min(tpm as f64);
+        }
+    }
+
+    /// How long until one request and `tokens` fit. Requests larger than the whole budget
+    /// only wait for a full bucket.
+    fn wait_for(&self, tokens: u32) -> Duration {
+        let wait = |available: f64, needed: f64, per_minute: Option<u32>| match per_minute {
+            Some(per_minute) => {
+                let needed = needed.min(per_minute as f64);
+                let missing = (needed - available).max(0.0);
+                Duration::from_secs_f64(missing / per_minute.max(1) as f64 * 60.0)
+            }
+            None => Duration::ZERO,
+        };
+        wait(self.requests, 1.0, self.limits.requests_per_minute).max(wait(
+            self.tokens,
+            tokens as f64,
+            self.limits.tokens_per_minute,
+        ))
+    }
+
+    /// Take one request and `tokens`, returning the tokens actually taken.
+    fn take(&mut self, tokens: u32) -> f64 {
+        if self.limits.requests_per_minute.is_some() {
+            self.requests -= 1.0;
+        }
+        let Some(tpm) = self.limits.tokens_per_minute else {
+            return 0.0;
+        };
+        let taken = (tokens as f64).min(tpm as f64);
+        self.tokens -= taken;
+        taken
+    }
+
+    fn waiting_above(&self, priority: Priority) -> bool {
+        self.waiting[priority as usize + 1..].iter().any(|n| *n > 0)
+    }
+}
+
+/// An up-front guess of the tokens a request will use.
+pub(crate) trait EstimateTokens {
+    fn estimated_tokens(&self) -> u32;
+}
+
+/// Roughly four characters per token for English text.
+fn text_tokens(text: &str) -> u32 {
+    (text.chars().count() as u32).div_ceil(4)
+}
+
+/// Parts other than text count as a low-detail image.
+fn content_tokens(content: &chat::Content) -> u32 {
+    match content {
+        chat::Content::Text(text) => text_tokens(text),
+        chat::Content::Parts(parts) => parts
+            .iter()
+            .map(|part| match part {
+                chat::ContentPart::Text { text } => text_tokens(text),
+                _ => 85,
+            })
+            .fold(0, u32::saturating_add),
+    }
+}
+
+impl EstimateTokens for chat::ChatArguments {
+    fn estimated_tokens(&self) -> u32 {
+        // Each message carries a few tokens of framing.
+        let prompt: u32 = self
+            .messages
+            .iter()
+            .map(|m| {
+                let calls = m.tool_calls.iter().flatten().map(|c| {
+                    text_tokens(&c.function.name).saturating_add(text_tokens(&c.function.arguments))
+                });
+                calls.fold(
+                    4u32.saturating_add(m.content.as_ref().map_or(0, content_tokens)),
+                    u32::saturating_add,
+                )
+            })
+            .fold(0, u32::saturating_add);
+        let max_tokens = self.max_completion_tokens.or(self.max_tokens);
+        prompt.saturating_add(max_tokens.unwrap_or(0).saturating_mul(self.n.unwrap_or(1)))
+    }
+}
+
+impl EstimateTokens for completions::CompletionArguments {
+    fn estimated_tokens(&self) -> u32 {
+        let prompt = self.prompt.as_deref().map_or(0, text_tokens);
+        prompt.saturating_add(
+            self.max_tokens
+                .unwrap_or(16)
+                .saturating_mul(self.n.unwrap_or(1)),
+        )
+    }
+}
+
+impl EstimateTokens for embeddings::EmbeddingsArguments {
+    fn estimated_tokens(&self) -> u32 {
+        text_tokens(&self.input)
+    }
+}
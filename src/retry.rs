@@ -0,0 +1,228 @@
+//! Automatic retries with exponential backoff.
+//!
+//! Every [Client](crate::Client) call goes through its [RetryPolicy]. A request is retried
+//! when it fails with a retryable status (429 and 5xx by default) or a connection/timeout error.
+//! Streaming calls are only retried before the response arrives, never once the first byte of
+//! the body has been handed to the caller.
+//!
+//! ```
+//! use std::time::Duration;
+//! use openai_rust2::retry::RetryPolicy;
+//!
+//! let policy = RetryPolicy::default()
+//!     .with_max_attempts(5)
+//!     .with_base_delay(Duration::from_millis(250))
+//!     .with_max_delay(Duration::from_secs(30));
+//! let client = openai_rust2::Client::new("sk-...").with_retry_policy(policy);
+//! ```
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+use crate::Error;
+
+/// Controls if and when a failed request is sent again.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry. Every following retry doubles it.
+    pub base_delay: Duration,
+    /// Upper bound for any delay, including delays requested by the server.
+    pub max_delay: Duration,
+    /// Fraction of each backoff delay that is randomized, between 0.0 and 1.0.
+    pub jitter: f64,
+    /// Response statuses that are retried.
+    pub retry_statuses: Vec<StatusCode>,
+    /// Retry when the connection could not be established.
+    pub retry_connect_errors: bool,
+    /// Retry when the request timed out.
+    pub retry_timeouts: bool,
+}
+
+impl Default for RetryPolicy {
+    /// Three attempts, starting at 500ms and capped at 8s, retrying 408, 409, 429 and 5xx.
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+            jitter: 0.25,
+            retry_statuses: vec![
+                StatusCode::REQUEST_TIMEOUT,
+                StatusCode::CONFLICT,
+                StatusCode::TOO_MANY_REQUESTS,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StatusCode::BAD_GATEWAY,
+                StatusCode::SERVICE_UNAVAILABLE,
+                StatusCode::GATEWAY_TIMEOUT,
+            ],
+            retry_connect_errors: true,
+            retry_timeouts: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries.
+    pub fn none() -> Self {
+        RetryPolicy {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = clamp_jitter(jitter);
+        self
+    }
+
+    pub fn with_retry_statuses(mut self, statuses: Vec<StatusCode>) -> Self {
+        self.retry_statuses = statuses;
+        self
+    }
+
+    pub fn with_retry_connect_errors(mut self, retry: bool) -> Self {
+        self.retry_connect_errors = retry;
+        self
+    }
+
+    pub fn with_retry_timeouts(mut self, retry: bool) -> Self {
+        self.retry_timeouts = retry;
+        self
+    }
+
+    /// Whether another attempt may follow attempt number `attempt` (starting at 1).
+    /// A `max_attempts` of 0 behaves like 1.
+    pub(crate) fn retries_left(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts.max(1)
+    }
+
+    pub(crate) fn should_retry_status(&self, status: StatusCode) -> bool {
+        self.retry_statuses.contains(&status)
+    }
+
+    pub(crate) fn should_retry_error(&self, e: &Error) -> bool {
+        match e {
+            Error::Transport(e) => {
+                (self.retry_connect_errors && e.is_connect())
+                    || (self.retry_timeouts && e.is_timeout())
+            }
+            _ => false,
+        }
+    }
+
+    /// The delay before retry number `retry` (starting at 1).
+    /// A delay requested by the server through `headers` takes precedence over the backoff.
+    pub(crate) fn delay(&self, retry: u32, headers: Option<&HeaderMap>) -> Duration {
+        if let Some(delay) = headers.and_then(server_delay) {
+            return delay.min(self.max_delay);
+        }
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+        exp.mul_f64(1.0 - clamp_jitter(self.jitter) * fastrand::f64())
+    }
+}
+
+/// Keep `jitter` within 0.0..=1.0, since the field can be set directly. NaN is no jitter.
+fn clamp_jitter(jitter: f64) -> f64 {
+    if jitter.is_nan() {
+        0.0
+    } else {
+        jitter.clamp(0.0, 1.0)
+    }
+}
+
+/// The delay requested by the server, from `retry-after-ms`, `retry-after`
+/// or the `x-ratelimit-reset-*` headers, in that order.
+pub(crate) fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    if let Some(delay) = header("retry-after-ms")
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .and_then(|ms| secs_f64(ms / 1000.0))
+    {
+        return Some(delay);
+    }
+    if let Some(value) = header("retry-after") {
+        let value = value.trim();
+        if let Some(delay) = value.parse::<f64>().ok().and_then(secs_f64) {
+            return Some(delay);
+        }
+        if let Ok(date) = httpdate::parse_http_date(value) {
+            return Some(
+                date.duration_since(SystemTime::now())
+                    .unwrap_or(Duration::ZERO),
+            );
+        }
+    }
+
+    // Prefer the reset of whichever budget is exhausted, otherwise wait for both.
+    let exhausted = |kind: &str| header(&format!("x-ratelimit-remaining-{}", kind)) == Some("0");
+    let reset =
+        |kind: &str| header(&format!("x-ratelimit-reset-{}", kind)).and_then(parse_duration);
+    let resets = ["requests", "tokens"];
+    resets
+        .iter()
+        .filter(|kind| exhausted(kind))
+        .filter_map(|kind| reset(kind))
+        .max()
+        .or_else(|| resets.iter().filter_map(|kind| reset(kind)).max())
+}
+
+/// Parse durations in the format used by the `x-ratelimit-reset-*` headers,
+/// e.g. `20ms`, `1s`, `1.5s` or `6m0s`. Plain numbers are seconds.
+pub(crate) fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return secs_f64(secs);
+    }
+
+    let mut total = Duration::ZERO;
+    let mut rest = value;
+    while !rest.is_empty() {
+        let num_end = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .filter(|&i| i > 0)?;
+        let num = rest[..num_end].parse::<f64>().ok()?;
+        rest = &rest[num_end..];
+        let unit_end = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let secs = match &rest[..unit_end] {
+            "h" => num * 3600.0,
+            "m" => num * 60.0,
+            "s" => num,
+            "ms" => num / 1000.0,
+            "us" | "µs" => num / 1_000_000.0,
+            "ns" => num / 1_000_000_000.0,
+            _ => return None,
+        };
+        total = total.checked_add(secs_f64(secs)?)?;
+        rest = &rest[unit_end..];
+    }
+    Some(total)
+}
+
+/// Negative seconds are zero. `None` for values too large for a [Duration], such as `1e400` or `inf`.
+pub(crate) fn secs_f64(secs: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(secs.max(0.0)).ok()
+}
@@ -0,0 +1,183 @@
+//! A decoder for [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html).
+//!
+//! Used for streamed chat completions, and usable for any other `text/event-stream` body.
+//! Bytes can be pushed in arbitrary pieces: lines are only decoded once complete,
+//! so multi-byte characters split across network chunks are handled.
+//!
+//! ```
+//! use openai_rust2::sse::Decoder;
+//!
+//! let mut decoder = Decoder::new();
+//! decoder.push(b": keep-alive\r\nevent: message\r\ndata: {\"a\":\r\n");
+//! assert!(decoder.next_event().is_none());
+//! decoder.push(b"data: 1}\r\n\r\n");
+//! let event = decoder.next_event().unwrap();
+//! assert_eq!(event.event.as_deref(), Some("message"));
+//! assert_eq!(event.data, "{\"a\":\n1}");
+//! ```
+use std::time::Duration;
+
+/// One dispatched event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The `event` field, `None` for the default `message` type.
+    pub event: Option<String>,
+    /// The `data` lines joined with `\n`.
+    pub data: String,
+    /// The last `id` seen so far in the stream.
+    pub id: Option<String>,
+}
+
+/// Incrementally splits a byte stream into [Event]s.
+///
+/// Received bytes are appended to one buffer, scanned for line endings once, and dropped
+/// in bulk, so decoding takes time linear in the size of the stream however it is chunked.
+#[derive(Debug, Default)]
+pub struct Decoder {
+    buf: Vec<u8>,
+    /// Start of the first unprocessed line in `buf`.
+    pos: usize,
+    /// How far past `pos` has been searched for a line ending without finding one.
+    scanned: usize,
+    /// Whether the byte order mark at the start of the stream has been checked.
+    started: bool,
+    fields: Fields,
+}
+
+/// The event being assembled.
+#[derive(Debug, Default)]
+struct Fields {
+    event: Option<String>,
+    data: String,
+    has_data: bool,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append received bytes.
+    pub fn push(&mut self, bytes: &[u8]) {
+        // Drop processed lines once they make up most of the buffer, so each byte is moved
+        // a bounded number of times.
+        if self.pos > 0 && self.pos >= self.buf.len() - self.pos {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// The next complete event, or `None` if more bytes are needed.
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.decode(false)
+    }
+
+    /// Call at the end of the stream to get an event that was not terminated by a blank line.
+    /// Many servers omit the final blank line, so unlike the specification this still dispatches it.
+    pub fn finish(&mut self) -> Option<Event> {
+        self.decode(true).or_else(|| self.fields.dispatch())
+    }
+
+    /// The reconnection time last sent in a `retry` field.
+    pub fn retry(&self) -> Option<Duration> {
+        self.fields.retry
+    }
+
+    fn decode(&mut self, eof: bool) -> Option<Event> {
+        while let Some((start, end)) = self.next_line(eof) {
+            let line = String::from_utf8_lossy(&self.buf[start..end]);
+            if let Some(event) = self.fields.process_line(&line) {
+                return Some(event);
+            }
+        }
+        None
+    }
+
+    /// The range of the next line in the buffer. At the end of the stream an unterminated line counts.
+    fn next_line(&mut self, eof: bool) -> Option<(usize, usize)> {
+        if !self.started {
+            const BOM: &[u8] = b"\xEF\xBB\xBF";
+            if self.buf.len() < BOM.len() && !eof && BOM.starts_with(&self.buf) {
+                return None;
+            }
+            if self.buf.starts_with(BOM) {
+                self.pos = BOM.len();
+            }
+            self.started = true;
+        }
+
+        let start = self.pos;
+        let rest = &self.buf[start + self.scanned..];
+        let (end, next) = match rest.iter().position(|b| *b == b'\n' || *b == b'\r') {
+            Some(i) => {
+                let end = start + self.scanned + i;
+                match (self.buf[end], self.buf.get(end + 1)) {
+                    (b'\r', Some(b'\n')) => (end, end + 2),
+                    // A trailing \r may be the first half of \r\n.
+                    (b'\r', None) if !eof => {
+                        self.scanned = end - start;
+                        return None;
+                    }
+                    _ => (end, end + 1),
+                }
+            }
+            None if eof && start < self.buf.len() => (self.buf.len(), self.buf.len()),
+            None => {
+                self.scanned = self.buf.len() - start;
+                return None;
+            }
+        };
+        self.pos = next;
+        self.scanned = 0;
+        Some((start, end))
+    }
+}
+
+impl Fields {
+    fn process_line(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // A comment, often sent as a keep-alive.
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = Some(value.to_owned()),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_owned()),
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<Event> {
+        let event = self.event.take();
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        Some(Event {
+            event: event.filter(|e| !e.is_empty()),
+            data: std::mem::take(&mut self.data),
+            id: self.last_id.clone(),
+        })
+    }
+}
@@ -0,0 +1,215 @@
+//! Spans around [Client](crate::Client) calls, recorded with the `tracing` feature.
+//! The fields are documented on [Client](crate::Client). Without the feature this compiles to nothing.
+use std::future::Future;
+
+use crate::{chat, completions, embeddings, images, middleware, models, Result};
+
+/// Token counts reported by a response.
+#[cfg_attr(not(feature = "tracing"), allow(dead_code))]
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Tokens {
+    pub prompt: Option<u32>,
+    pub completion: Option<u32>,
+    pub total: Option<u32>,
+}
+
+/// Responses that carry token usage.
+pub(crate) trait ReportsUsage {
+    fn tokens(&self) -> Option<Tokens> {
+        None
+    }
+}
+
+impl ReportsUsage for chat::ChatCompletion {
+    fn tokens(&self) -> Option<Tokens> {
+        Some(Tokens {
+            prompt: Some(self.usage.prompt_tokens),
+            completion: Some(self.usage.completion_tokens),
+            total: Some(self.usage.total_tokens),
+        })
+    }
+}
+
+impl ReportsUsage for chat::ResponsesCompletion {
+    fn tokens(&self) -> Option<Tokens> {
+        Some(Tokens {
+            prompt: Some(self.usage.input_tokens),
+            completion: Some(self.usage.output_tokens),
+            total: Some(self.usage.total_tokens),
+        })
+    }
+}
+
+impl ReportsUsage for completions::CompletionResponse {
+    fn tokens(&self) -> Option<Tokens> {
+        Some(Tokens {
+            prompt: Some(self.usage.prompt_tokens),
+            completion: Some(self.usage.completion_tokens),
+            total: Some(self.usage.total_tokens),
+        })
+    }
+}
+
+impl ReportsUsage for embeddings::EmbeddingsResponse {
+    fn tokens(&self) -> Option<Tokens> {
+        Some(Tokens {
+            prompt: Some(self.usage.prompt_tokens),
+            completion: None,
+            total: Some(self.usage.total_tokens),
+        })
+    }
+}
+
+/// Only the final chunk of a stream with [include_usage](chat::StreamOptions::include_usage) has usage.
+impl ReportsUsage for chat::stream::ChatCompletionChunk {
+    fn tokens(&self) -> Option<Tokens> {
+        self.usage.as_ref().map(|usage| Tokens {
+            prompt: Some(usage.prompt_tokens),
+            completion: Some(usage.completion_tokens),
+            total: Some(usage.total_tokens),
+        })
+    }
+}
+
+impl ReportsUsage for models::ListModelsResponse {}
+impl ReportsUsage for images::ImageResponse {}
+impl ReportsUsage for middleware::Response {}
+
+#[cfg(feature = "tracing")]
+pub(crate) use enabled::{record_response, CallSpan};
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) use disabled::{record_response, CallSpan};
+
+#[cfg(feature = "tracing")]
+mod enabled {
+    use std::time::Instant;
+
+    use tracing::field::Empty;
+    use tracing::Instrument;
+
+    use super::*;
+
+    /// The span of one call.
+    #[derive(Debug)]
+    pub(crate) struct CallSpan {
+        span: tracing::Span,
+        start: Instant,
+        chunks: u64,
+    }
+
+    impl CallSpan {
+        pub(crate) fn new(endpoint: &'static str, model: Option<&str>) -> Self {
+            let span = tracing::info_span!(
+                "openai",
+                endpoint,
+                model,
+                status = Empty,
+                request_id = Empty,
+                latency_ms = Empty,
+                prompt_tokens = Empty,
+                completion_tokens = Empty,
+                total_tokens = Empty,
+                time_to_first_chunk_ms = Empty,
+                chunks = Empty,
+            );
+            Self {
+                span,
+                start: Instant::now(),
+                chunks: 0,
+            }
+        }
+
+        /// Run `call` inside the span and record its outcome.
+        pub(crate) async fn run<T: ReportsUsage>(
+            &self,
+            call: impl Future<Output = Result<T>>,
+        ) -> Result<T> {
+            let res = call.instrument(self.span.clone()).await;
+            self.span
+                .record("latency_ms", self.start.elapsed().as_millis() as u64);
+            match &res {
+                Ok(value) => self.record_tokens(value.tokens()),
+                Err(e) => {
+                    if let Some(status) = e.status() {
+                        self.span.record("status", status.as_u16());
+                    }
+                    if let Some(request_id) = e.request_id() {
+                        self.span.record("request_id", request_id);
+                    }
+                }
+            }
+            res
+        }
+
+        /// Count a streamed chunk, recording the time to the first one.
+        pub(crate) fn chunk<T: ReportsUsage>(&mut self, chunk: &T) {
+            if self.chunks == 0 {
+                self.span.record(
+                    "time_to_first_chunk_ms",
+                    self.start.elapsed().as_millis() as u64,
+                );
+            }
+            self.chunks += 1;
+            self.record_tokens(chunk.tokens());
+        }
+
+        /// Record the end of a stream.
+        pub(crate) fn finish(&self) {
+            self.span.record("chunks", self.chunks);
+        }
+
+        fn record_tokens(&self, tokens: Option<Tokens>) {
+            let Some(tokens) = tokens else { return };
+            if let Some(prompt) = tokens.prompt {
+                self.span.record("prompt_tokens", prompt);
+            }
+            if let Some(completion) = tokens.completion {
+                self.span.record("completion_tokens", completion);
+            }
+            if let Some(total) = tokens.total {
+                self.span.record("total_tokens", total);
+            }
+        }
+    }
+
+    /// Record the final response of a call on the current span.
+    pub(crate) fn record_response(res: &middleware::Response) {
+        let span = tracing::Span::current();
+        span.record("status", res.status.as_u16());
+        if let Some(request_id) = res
+            .headers
+            .get("x-request-id")
+            .and_then(|v| v.to_str().ok())
+        {
+            span.record("request_id", request_id);
+        }
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+mod disabled {
+    use super::*;
+
+    #[derive(Debug)]
+    pub(crate) struct CallSpan;
+
+    impl CallSpan {
+        pub(crate) fn new(_endpoint: &'static str, _model: Option<&str>) -> Self {
+            CallSpan
+        }
+
+        pub(crate) async fn run<T: ReportsUsage>(
+            &self,
+            call: impl Future<Output = Result<T>>,
+        ) -> Result<T> {
+            call.await
+        }
+
+        pub(crate) fn chunk<T: ReportsUsage>(&mut self, _chunk: &T) {}
+
+        pub(crate) fn finish(&self) {}
+    }
+
+    pub(crate) fn record_response(_res: &middleware::Response) {}
+}
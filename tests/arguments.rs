@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+
+use openai_rust::chat::{
+    ChatArguments, ChatCompletion, Content, Message, Modality, Prediction, ReasoningEffort,
+    ServiceTier, Verbosity,
+};
+use openai_rust2 as openai_rust;
+use serde_json::json;
+
+#[test]
+pub fn all_parameters_are_serialized() {
+    let args = ChatArguments::new("gpt-5", vec![Message::user("Hi")])
+        .with_seed(42)
+        .with_logit_bias(HashMap::from([(50256, -100)]))
+        .with_top_logprobs(2)
+        .with_stop(vec!["\n\n", "END"])
+        .with_max_completion_tokens(256)
+        .with_reasoning_effort(ReasoningEffort::Minimal)
+        .with_verbosity(Verbosity::Low)
+        .with_service_tier(ServiceTier::Flex)
+        .with_store(true)
+        .with_metadata(HashMap::from([("team".to_owned(), "search".to_owned())]))
+        .with_modalities(vec![Modality::Text])
+        .with_prediction(Prediction::Content {
+            content: Content::from("fn main() {}"),
+        })
+        .with_prompt_cache_key("support-v1")
+        .with_safety_identifier("user-hash");
+    assert_eq!(
+        serde_json::to_value(&args).unwrap(),
+        json!({
+            "model": "gpt-5",
+            "messages": [{"role": "user", "content": "Hi"}],
+            "seed": 42,
+            "logit_bias": {"50256": -100},
+            "logprobs": true,
+            "top_logprobs": 2,
+            "stop": ["\n\n", "END"],
+            "max_completion_tokens": 256,
+            "reasoning_effort": "minimal",
+            "verbosity": "low",
+            "service_tier": "flex",
+            "store": true,
+            "metadata": {"team": "search"},
+            "modalities": ["text"],
+            "prediction": {"type": "content", "content": "fn main() {}"},
+            "prompt_cache_key": "support-v1",
+            "safety_identifier": "user-hash",
+        })
+    );
+
+    let args = ChatArguments::new("gpt-4o", vec![]).with_stop("\n");
+    assert_eq!(serde_json::to_value(&args).unwrap()["stop"], "\n");
+}
+
+#[test]
+pub fn response_metadata_is_parsed() {
+    let completion: ChatCompletion = serde_json::from_value(json!({
+        "id": "c", "created": 1, "model": "m",
+        "system_fingerprint": "fp_44709d6fcb",
+        "service_tier": "default",
+        "choices": [{
+            "index": 0,
+            "message": {"role": "assistant", "content": "Hi"},
+            "finish_reason": "stop",
+            "logprobs": {"content": [{"token": "Hi", "logprob": -0.5, "bytes": [72, 105], "top_logprobs": []}]},
+        }],
+    }))
+    .unwrap();
+    assert_eq!(
+        completion.system_fingerprint.as_deref(),
+        Some("fp_44709d6fcb")
+    );
+    assert_eq!(completion.service_tier.as_deref(), Some("default"));
+    let logprobs = completion.choices[0].logprobs.as_ref().unwrap();
+    assert_eq!(logprobs.content.as_ref().unwrap()[0].token, "Hi");
+}
@@ -0,0 +1,108 @@
+use openai_rust::azure::{AzureAuth, AzureConfig};
+use openai_rust::futures_util::StreamExt;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{bearer_token, body_partial_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args_with, CHAT_COMPLETION};
+
+fn azure_client(server: &MockServer, auth: AzureAuth) -> openai_rust::Client {
+    openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_api_key("azure-key")
+        .with_azure(
+            AzureConfig::new("2024-10-21")
+                .with_deployment("gpt-4o", "my-gpt-4o")
+                .with_auth(auth),
+        )
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+pub async fn chat_uses_deployment_url_and_api_key_header() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/my-gpt-4o/chat/completions"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(header("api-key", "azure-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/my-gpt-4o/chat/completions"))
+        .and(body_partial_json(serde_json::json!({"stream": true})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "data: {\"id\":\"chatcmpl-1\",\"created\":1700000000,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi!\"},\"finish_reason\":null}]}\n\n",
+        ))
+        .with_priority(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = azure_client(&server, AzureAuth::ApiKey);
+    assert_eq!(
+        c.create_chat(chat_args_with("gpt-4o", "Hello GPT!"), None)
+            .await
+            .unwrap()
+            .to_string(),
+        "Hi!"
+    );
+    let chunks = c
+        .create_chat_stream(chat_args_with("gpt-4o", "Hello GPT!"), None)
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(chunks[0].as_ref().unwrap().to_string(), "Hi!");
+}
+
+#[tokio::test]
+pub async fn embeddings_with_entra_token_and_unmapped_model() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/deployments/text-embedding-3-small/embeddings"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(bearer_token("azure-key"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"object":"list","data":[{"object":"embedding","embedding":[0.1,0.2],"index":0}],"model":"text-embedding-3-small","usage":{"prompt_tokens":8,"total_tokens":8}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = azure_client(&server, AzureAuth::EntraId);
+    let args = openai_rust::embeddings::EmbeddingsArguments::new(
+        "text-embedding-3-small",
+        "The food was delicious and the waiter...".to_owned(),
+    );
+    c.create_embeddings(args, None).await.unwrap();
+}
+
+#[tokio::test]
+pub async fn responses_send_deployment_as_model() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/openai/responses"))
+        .and(query_param("api-version", "2024-10-21"))
+        .and(body_partial_json(serde_json::json!({"model": "my-gpt-4o"})))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"id":"resp_1","output":[{"type":"message","role":"assistant","content":[{"type":"output_text","text":"Hi!"}]}],"usage":{"input_tokens":5,"output_tokens":2,"total_tokens":7}}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = azure_client(&server, AzureAuth::ApiKey);
+    let args = openai_rust::chat::OpenAIResponsesArguments::new(
+        "gpt-4o",
+        vec![openai_rust::chat::ResponsesMessage {
+            role: openai_rust::chat::Role::User,
+            content: "Hello GPT!".to_owned(),
+        }],
+    );
+    let res = c.create_openai_responses(args, None).await.unwrap();
+    assert_eq!(res.get_text_content(), "Hi!");
+}
@@ -0,0 +1,37 @@
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const MODELS: &str = r#"{"object":"list","data":[{"id":"gpt-4o","object":"model","created":1715367049,"owned_by":"system"}]}"#;
+
+async fn models_server(models_path: &str) -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path(models_path))
+        .respond_with(ResponseTemplate::new(200).set_body_string(MODELS))
+        .expect(1)
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+pub async fn base_url_path_prefix_is_kept() {
+    let server = models_server("/openai/v1/models").await;
+    let c = openai_rust::Client::new_with_base_url("", &format!("{}/openai/v1/", server.uri()));
+    c.list_models(None).await.unwrap();
+}
+
+#[tokio::test]
+pub async fn base_url_without_path_gets_v1() {
+    let server = models_server("/v1/models").await;
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    c.list_models(None).await.unwrap();
+}
+
+#[tokio::test]
+pub async fn opt_url_path_replaces_the_path() {
+    let server = models_server("/api/models").await;
+    let c = openai_rust::Client::new_with_base_url("", &format!("{}/openai/v1", server.uri()));
+    c.list_models(Some("/api/models".to_owned())).await.unwrap();
+}
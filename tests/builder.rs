@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use openai_rust::Error;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{bearer_token, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[test]
+pub fn invalid_configuration_is_an_error() {
+    let err = openai_rust::Client::builder()
+        .with_base_url("not a url")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Config(_)));
+
+    let err = openai_rust::Client::builder()
+        .with_header("X-Bad", "line\nbreak")
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Config(_)));
+
+    let err = openai_rust::Client::builder()
+        .with_http_client(reqwest::Client::new())
+        .with_connect_timeout(Duration::from_secs(1))
+        .build()
+        .err()
+        .unwrap();
+    assert!(matches!(err, Error::Config(_)));
+}
+
+#[tokio::test]
+pub async fn sends_configured_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .and(bearer_token("sk-test"))
+        .and(header("OpenAI-Organization", "org-123"))
+        .and(header("OpenAI-Project", "proj_456"))
+        .and(header("X-Team", "search"))
+        .and(header("User-Agent", "my-service/1.0"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"{"object":"list","data":[{"id":"gpt-4o","object":"model","created":1715367049,"owned_by":"system"}]}"#,
+        ))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::builder()
+        .with_api_key("sk-test")
+        .with_base_url(server.uri())
+        .with_organization("org-123")
+        .with_project("proj_456")
+        .with_header("X-Team", "search")
+        .with_user_agent("my-service/1.0")
+        .with_timeout(Duration::from_secs(10))
+        .build()
+        .unwrap();
+    let models = c.list_models(None).await.unwrap();
+    assert_eq!(models[0].id, "gpt-4o");
+}
@@ -0,0 +1,116 @@
+use openai_rust::cassette::{Cassette, Mode};
+use openai_rust::futures_util::StreamExt;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args, chat_args_with, CHAT_COMPLETION, CHAT_STREAM};
+
+fn cassette_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!(
+        "openai-rust2-cassette-{}-{}.json",
+        name,
+        std::process::id()
+    ))
+}
+
+#[tokio::test]
+pub async fn recorded_interactions_replay_without_the_server() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req_123")
+                .set_body_string(CHAT_COMPLETION),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let file = cassette_path("replay");
+
+    let c = openai_rust::Client::builder()
+        .with_api_key("sk-secret")
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_cassette(Cassette::new(&file, Mode::Record).unwrap())
+        .build()
+        .unwrap();
+    let recorded = c.create_chat(chat_args(), None).await.unwrap();
+    drop(server);
+
+    let contents = std::fs::read_to_string(&file).unwrap();
+    assert!(contents.contains("req_123"));
+    assert!(!contents.contains("sk-secret"));
+
+    let c = openai_rust::Client::builder()
+        .with_base_url("http://127.0.0.1:9/v1")
+        .with_cassette(Cassette::replay(&file).unwrap())
+        .build()
+        .unwrap();
+    let replayed = c.create_chat(chat_args(), None).await.unwrap();
+    assert_eq!(replayed.to_string(), recorded.to_string());
+
+    let err = c
+        .create_chat(chat_args_with("gpt-3.5-turbo", "Bye"), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, openai_rust::Error::Cassette(_)));
+    assert!(err
+        .to_string()
+        .contains("no recorded interaction for POST /v1/chat/completions"));
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[tokio::test]
+pub async fn streamed_responses_are_recorded_and_replayed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(CHAT_STREAM),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let file = cassette_path("stream");
+
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_cassette(Cassette::record(&file).unwrap())
+        .build()
+        .unwrap();
+    let recorded = c
+        .create_chat_stream(chat_args(), None)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().to_string())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(recorded, ["Hi", "!"]);
+
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_cassette(Cassette::replay(&file).unwrap())
+        .build()
+        .unwrap();
+    let replayed = c
+        .create_chat_stream(chat_args(), None)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap().to_string())
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(replayed, recorded);
+
+    std::fs::remove_file(file).unwrap();
+}
+
+#[test]
+pub fn missing_cassette_is_an_error() {
+    let err = Cassette::replay(cassette_path("missing")).unwrap_err();
+    assert!(matches!(err, openai_rust::Error::Cassette(_)));
+}
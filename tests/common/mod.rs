@@ -0,0 +1,28 @@
+//! Responses and arguments shared by the integration tests.
+// Each test crate only uses some of them.
+#![allow(dead_code)]
+use openai_rust2::chat::{ChatArguments, Message};
+
+pub const CHAT_COMPLETION: &str = r#"{"id":"chatcmpl-1","object":"chat.completion","created":1700000000,"model":"gpt-3.5-turbo","choices":[{"index":0,"message":{"role":"assistant","content":"Hi!"},"finish_reason":"stop"}],"usage":{"prompt_tokens":9,"completion_tokens":2,"total_tokens":11}}"#;
+
+/// The streamed form of [CHAT_COMPLETION], without usage.
+pub const CHAT_STREAM: &str = "data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n\
+data: {\"id\":\"chatcmpl-1\",\"object\":\"chat.completion.chunk\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"!\"},\"finish_reason\":\"stop\"}]}\n\n\
+data: [DONE]\n\n";
+
+/// [CHAT_COMPLETION] reporting `total_tokens` of usage, 9 of them for the prompt.
+pub fn chat_completion_using(total_tokens: u32) -> String {
+    let mut completion: serde_json::Value = serde_json::from_str(CHAT_COMPLETION).unwrap();
+    completion["usage"]["completion_tokens"] = total_tokens.saturating_sub(9).into();
+    completion["usage"]["total_tokens"] = total_tokens.into();
+    completion.to_string()
+}
+
+pub fn chat_args() -> ChatArguments {
+    chat_args_with("gpt-3.5-turbo", "Hello GPT!")
+}
+
+/// One user message with `content`.
+pub fn chat_args_with(model: &str, content: &str) -> ChatArguments {
+    ChatArguments::new(model, vec![Message::user(content)])
+}
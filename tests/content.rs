@@ -0,0 +1,60 @@
+use openai_rust::chat::{data_url, sniff_mime, Content, ContentPart, ImageDetail, Message};
+use openai_rust2 as openai_rust;
+use serde_json::json;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+#[test]
+pub fn text_content_is_still_a_string() {
+    let message = Message::user("Hello GPT!");
+    assert_eq!(
+        serde_json::to_value(&message).unwrap(),
+        json!({"role": "user", "content": "Hello GPT!"})
+    );
+    let message: Message =
+        serde_json::from_value(json!({"role": "assistant", "content": "Hi"})).unwrap();
+    assert_eq!(message.content, Some(Content::Text("Hi".to_owned())));
+}
+
+#[test]
+pub fn parts_serialize_as_typed_objects() {
+    let content = Content::from(vec![
+        ContentPart::text("Describe these."),
+        ContentPart::image_url("https://example.com/a.jpg").with_detail(ImageDetail::Low),
+        ContentPart::image_bytes(PNG).unwrap(),
+        ContentPart::input_audio(b"RIFF\0\0\0\0WAVE", "wav"),
+        ContentPart::file_id("file-1"),
+        ContentPart::file_bytes("a.pdf", b"%PDF-1.7"),
+    ]);
+    let value = serde_json::to_value(&content).unwrap();
+    assert_eq!(
+        value,
+        json!([
+            {"type": "text", "text": "Describe these."},
+            {"type": "image_url", "image_url": {"url": "https://example.com/a.jpg", "detail": "low"}},
+            {"type": "image_url", "image_url": {"url": data_url("image/png", PNG)}},
+            {"type": "input_audio", "input_audio": {"data": "UklGRgAAAABXQVZF", "format": "wav"}},
+            {"type": "file", "file": {"file_id": "file-1"}},
+            {"type": "file", "file": {"file_data": "data:application/pdf;base64,JVBERi0xLjc=", "filename": "a.pdf"}},
+        ])
+    );
+    assert_eq!(serde_json::from_value::<Content>(value).unwrap(), content);
+    assert_eq!(content.to_string(), "Describe these.");
+    assert_eq!(content.as_text(), None);
+}
+
+#[test]
+pub fn mime_sniffing() {
+    assert_eq!(sniff_mime(PNG), Some("image/png"));
+    assert_eq!(sniff_mime(b"\xFF\xD8\xFF\xE0"), Some("image/jpeg"));
+    assert_eq!(sniff_mime(b"GIF89a"), Some("image/gif"));
+    assert_eq!(sniff_mime(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+    assert_eq!(sniff_mime(b"ID3\x04"), Some("audio/mpeg"));
+    assert_eq!(sniff_mime(b"plain text"), None);
+    assert!(ContentPart::image_bytes(b"%PDF-1.7").is_none());
+
+    let path = std::env::temp_dir().join("openai_rust2_not_an_image.txt");
+    std::fs::write(&path, "text").unwrap();
+    let err = ContentPart::image_file(&path).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}
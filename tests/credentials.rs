@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use openai_rust::credentials::{AuthHeader, Credential, EnvVar, FileKey, RefreshingProvider};
+use openai_rust::Error;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{bearer_token, header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const MODELS: &str = r#"{"object":"list","data":[{"id":"gpt-4o","object":"model","created":1715367049,"owned_by":"system"}]}"#;
+
+async fn models_server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(MODELS))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+pub async fn refreshing_provider_caches_until_expiry() {
+    let server = MockServer::start().await;
+    for token in ["token-0", "token-1"] {
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(bearer_token(token))
+            .respond_with(ResponseTemplate::new(200).set_body_string(MODELS))
+            .expect(2)
+            .mount(&server)
+            .await;
+    }
+
+    let refreshes = Arc::new(AtomicUsize::new(0));
+    let counter = refreshes.clone();
+    let provider = RefreshingProvider::new(move || {
+        let n = counter.fetch_add(1, Ordering::SeqCst);
+        async move {
+            Ok(Credential::bearer(format!("token-{}", n))
+                .with_expires_in(Duration::from_millis(300)))
+        }
+    })
+    .with_refresh_margin(Duration::from_millis(100));
+    let c = openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_credentials(provider)
+        .build()
+        .unwrap();
+
+    c.list_models(None).await.unwrap();
+    c.list_models(None).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    c.list_models(None).await.unwrap();
+    c.list_models(None).await.unwrap();
+    assert_eq!(refreshes.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+pub async fn file_key_is_reread_when_changed() {
+    let server = MockServer::start().await;
+    for key in ["key-a", "key-b"] {
+        Mock::given(method("GET"))
+            .and(path("/v1/models"))
+            .and(header("x-api-key", key))
+            .respond_with(ResponseTemplate::new(200).set_body_string(MODELS))
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+
+    let file = std::env::temp_dir().join(format!("openai-rust2-key-{}", std::process::id()));
+    std::fs::write(&file, "key-a\n").unwrap();
+    let c = openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_credentials(FileKey::new(&file).with_header(AuthHeader::Custom("x-api-key".into())))
+        .build()
+        .unwrap();
+    c.list_models(None).await.unwrap();
+
+    // Make sure the modification time changes even on file systems with coarse timestamps.
+    std::thread::sleep(Duration::from_millis(20));
+    std::fs::write(&file, "key-b\n").unwrap();
+    let later = std::time::SystemTime::now() + Duration::from_secs(5);
+    std::fs::File::options()
+        .write(true)
+        .open(&file)
+        .unwrap()
+        .set_modified(later)
+        .unwrap();
+    c.list_models(None).await.unwrap();
+    std::fs::remove_file(&file).unwrap();
+}
+
+#[tokio::test]
+pub async fn missing_env_var_is_a_credential_error() {
+    let server = models_server().await;
+    let c = openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_credentials(EnvVar::new("OPENAI_RUST2_TEST_UNSET_KEY"))
+        .build()
+        .unwrap();
+    assert!(matches!(
+        c.list_models(None).await.unwrap_err(),
+        Error::Credential(_)
+    ));
+}
@@ -0,0 +1,81 @@
+use openai_rust::retry::RetryPolicy;
+use openai_rust::Error;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::chat_args;
+
+#[tokio::test]
+pub async fn api_error_is_parsed() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("x-request-id", "req_123")
+                .set_body_string(
+                    r#"{"error":{"message":"Rate limit reached","type":"requests","param":null,"code":"rate_limit_exceeded"}}"#,
+                ),
+        )
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri())
+        .with_retry_policy(RetryPolicy::none());
+    let err = c.create_chat(chat_args(), None).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(429));
+    assert_eq!(err.request_id(), Some("req_123"));
+    match err {
+        Error::Api(e) => {
+            assert_eq!(e.message(), Some("Rate limit reached"));
+            assert_eq!(e.error_type(), Some("requests"));
+            assert_eq!(e.code(), Some("rate_limit_exceeded"));
+            assert_eq!(e.param(), None);
+            assert!(e.body.contains("Rate limit reached"));
+        }
+        e => panic!("expected an API error, got {:?}", e),
+    }
+}
+
+#[tokio::test]
+pub async fn non_json_api_error_keeps_body() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri())
+        .with_retry_policy(RetryPolicy::none());
+    match c.list_models(None).await.unwrap_err() {
+        Error::Api(e) => {
+            assert!(e.error.is_none());
+            assert_eq!(e.body, "Bad Gateway");
+        }
+        e => panic!("expected an API error, got {:?}", e),
+    }
+}
+
+#[tokio::test]
+pub async fn any_2xx_is_success_and_bad_json_is_decode_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(201).set_body_string("{not json"))
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri())
+        .with_retry_policy(RetryPolicy::none());
+    let args = openai_rust::embeddings::EmbeddingsArguments::new(
+        "text-embedding-ada-002",
+        "The food was delicious and the waiter...".to_owned(),
+    );
+    match c.create_embeddings(args, None).await.unwrap_err() {
+        Error::Decode { body, .. } => assert_eq!(body, "{not json"),
+        e => panic!("expected a decode error, got {:?}", e),
+    }
+}
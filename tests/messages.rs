@@ -0,0 +1,46 @@
+use openai_rust::chat::{Message, ResponsesMessage, Role};
+use openai_rust2 as openai_rust;
+use serde_json::json;
+
+#[test]
+pub fn roles_and_constructors() {
+    for (role, name) in [
+        (Role::System, "system"),
+        (Role::Developer, "developer"),
+        (Role::User, "user"),
+        (Role::Assistant, "assistant"),
+        (Role::Tool, "tool"),
+        (Role::Other("critic".to_owned()), "critic"),
+    ] {
+        assert_eq!(serde_json::to_value(&role).unwrap(), name);
+        assert_eq!(serde_json::from_value::<Role>(json!(name)).unwrap(), role);
+        assert_eq!(Role::from(name), role);
+    }
+
+    let messages = [
+        Message::system("Be brief."),
+        Message::developer("No emojis."),
+        Message::user("Hi"),
+        Message::assistant("Hello!"),
+        Message::tool("call_1", "42"),
+    ];
+    assert_eq!(
+        serde_json::to_value(&messages).unwrap(),
+        json!([
+            {"role": "system", "content": "Be brief."},
+            {"role": "developer", "content": "No emojis."},
+            {"role": "user", "content": "Hi"},
+            {"role": "assistant", "content": "Hello!"},
+            {"role": "tool", "content": "42", "tool_call_id": "call_1"},
+        ])
+    );
+
+    let message = ResponsesMessage {
+        role: Role::User,
+        content: "Hi".to_owned(),
+    };
+    assert_eq!(
+        serde_json::to_value(message).unwrap(),
+        json!({"role": "user", "content": "Hi"})
+    );
+}
@@ -0,0 +1,102 @@
+use std::time::Duration;
+
+use openai_rust::meta::WithMeta;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args, CHAT_COMPLETION};
+
+#[tokio::test]
+pub async fn metadata_is_parsed_from_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req_123")
+                .insert_header("openai-processing-ms", "212")
+                .insert_header("x-ratelimit-limit-requests", "10000")
+                .insert_header("x-ratelimit-limit-tokens", "2000000")
+                .insert_header("x-ratelimit-remaining-requests", "9999")
+                .insert_header("x-ratelimit-remaining-tokens", "1999985")
+                .insert_header("x-ratelimit-reset-requests", "6ms")
+                .insert_header("x-ratelimit-reset-tokens", "1m30s")
+                .set_body_string(CHAT_COMPLETION),
+        )
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let res = WithMeta::capture(c.create_chat(chat_args(), None))
+        .await
+        .unwrap();
+
+    assert_eq!(res.to_string(), "Hi!");
+    let meta = res.meta.unwrap();
+    assert_eq!(meta.status, 200);
+    assert_eq!(meta.request_id.as_deref(), Some("req_123"));
+    assert_eq!(meta.processing_time, Some(Duration::from_millis(212)));
+    assert_eq!(meta.headers["x-request-id"], "req_123");
+    let rate_limit = &meta.rate_limit;
+    assert_eq!(rate_limit.limit_requests, Some(10000));
+    assert_eq!(rate_limit.limit_tokens, Some(2000000));
+    assert_eq!(rate_limit.remaining_requests, Some(9999));
+    assert_eq!(rate_limit.remaining_tokens, Some(1999985));
+    assert_eq!(rate_limit.reset_requests, Some(Duration::from_millis(6)));
+    assert_eq!(rate_limit.reset_tokens, Some(Duration::from_secs(90)));
+}
+
+#[tokio::test]
+pub async fn latency_covers_retries_and_missing_headers_are_none() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(503).insert_header("retry-after-ms", "50"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(r#"{"data":[]}"#))
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let res = WithMeta::capture(c.list_models(None)).await.unwrap();
+
+    assert!(res.body.is_empty());
+    let meta = res.meta.unwrap();
+    assert_eq!(meta.status, 200);
+    assert!(meta.latency >= Duration::from_millis(50));
+    assert_eq!(meta.request_id, None);
+    assert_eq!(meta.rate_limit, Default::default());
+}
+
+#[tokio::test]
+pub async fn out_of_range_headers_and_missing_responses_are_none() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("openai-processing-ms", "1e400")
+                .insert_header("x-ratelimit-reset-tokens", "inf")
+                .set_body_string(CHAT_COMPLETION),
+        )
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let res = WithMeta::capture(c.create_chat(chat_args(), None))
+        .await
+        .unwrap();
+    let meta = res.meta.unwrap();
+    assert_eq!(meta.processing_time, None);
+    assert_eq!(meta.rate_limit.reset_tokens, None);
+
+    let res = WithMeta::capture(async { Ok(1) }).await.unwrap();
+    assert_eq!(res.body, 1);
+    assert!(res.meta.is_none());
+}
@@ -0,0 +1,139 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use openai_rust::futures_util::StreamExt;
+use openai_rust::middleware::{from_fn, Body, Response};
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args, CHAT_COMPLETION};
+
+#[tokio::test]
+pub async fn middleware_runs_in_order_and_can_modify_requests() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .and(header("x-trace", "outer"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let c = openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_middleware(from_fn(|mut req, next| {
+            Box::pin(async move {
+                req.headers.insert("x-trace", "outer".parse().unwrap());
+                let mut body = req.json::<serde_json::Value>()?.unwrap();
+                body["temperature"] = 0.5.into();
+                req.set_json(&body)?;
+                next.run(req).await
+            })
+        }))
+        .with_middleware(from_fn(move |req, next| {
+            let log = log.clone();
+            Box::pin(async move {
+                let body = req.json::<serde_json::Value>()?.unwrap();
+                log.lock().unwrap().push((
+                    req.endpoint,
+                    req.headers.contains_key("x-trace"),
+                    body["temperature"].clone(),
+                ));
+                let res = next.run(req).await?;
+                let body = res.body.bytes().await?;
+                let mut completion = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+                completion["choices"][0]["message"]["content"] = "[redacted]".into();
+                Ok(Response::new(
+                    res.status.as_u16(),
+                    serde_json::to_vec(&completion).unwrap(),
+                ))
+            })
+        }))
+        .build()
+        .unwrap();
+
+    let res = c.create_chat(chat_args(), None).await.unwrap();
+    assert_eq!(res.to_string(), "[redacted]");
+    assert_eq!(
+        *seen.lock().unwrap(),
+        vec![("chat/completions", true, serde_json::json!(0.5))]
+    );
+}
+
+#[tokio::test]
+pub async fn middleware_can_short_circuit_and_sees_every_retry() {
+    let attempts = Arc::new(AtomicUsize::new(0));
+    let counter = attempts.clone();
+    let c = openai_rust::Client::builder()
+        .with_base_url("http://127.0.0.1:9")
+        .with_middleware(from_fn(move |_req, _next| {
+            let n = counter.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                Ok(match n {
+                    0 => {
+                        let mut res = Response::new(429, "");
+                        res.headers.insert("retry-after-ms", "1".parse().unwrap());
+                        res
+                    }
+                    _ => Response::new(200, CHAT_COMPLETION),
+                })
+            })
+        }))
+        .build()
+        .unwrap();
+
+    let res = c.create_chat(chat_args(), None).await.unwrap();
+    assert_eq!(res.to_string(), "Hi!");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+pub async fn middleware_sees_streaming_responses() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "data: {\"id\":\"chatcmpl-1\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi!\"},\"finish_reason\":null}]}\n\n",
+        ))
+        .mount(&server)
+        .await;
+
+    let streamed = Arc::new(AtomicUsize::new(0));
+    let counter = streamed.clone();
+    let c = openai_rust::Client::builder()
+        .with_base_url(server.uri())
+        .with_middleware(from_fn(move |req, next| {
+            let counter = counter.clone();
+            Box::pin(async move {
+                assert!(req.stream);
+                let mut res = next.run(req).await?;
+                if let Body::Stream(stream) = res.body {
+                    res.body = Body::Stream(
+                        stream
+                            .inspect(move |chunk| {
+                                if let Ok(chunk) = chunk {
+                                    counter.fetch_add(chunk.len(), Ordering::SeqCst);
+                                }
+                            })
+                            .boxed(),
+                    );
+                }
+                Ok(res)
+            })
+        }))
+        .build()
+        .unwrap();
+
+    let chunks = c
+        .create_chat_stream(chat_args(), None)
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(chunks[0].as_ref().unwrap().to_string(), "Hi!");
+    assert!(streamed.load(Ordering::SeqCst) > 0);
+}
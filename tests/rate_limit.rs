@@ -0,0 +1,202 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use openai_rust::rate_limit::{Limits, OnLimit, Priority, RateLimiter};
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args_with, chat_completion_using, CHAT_COMPLETION};
+
+/// Estimated at 6 tokens for the message plus `max_tokens`.
+fn chat_args(model: &str, max_tokens: u32) -> openai_rust::chat::ChatArguments {
+    chat_args_with(model, "Hello").with_max_tokens(max_tokens)
+}
+
+async fn server() -> MockServer {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .mount(&server)
+        .await;
+    server
+}
+
+#[tokio::test]
+pub async fn fail_fast_reports_the_exhausted_model() {
+    let server = server().await;
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_rate_limiter(
+            RateLimiter::new()
+                .with_limits("gpt-4o", Limits::new().with_requests_per_minute(1))
+                .with_on_limit(OnLimit::FailFast),
+        )
+        .build()
+        .unwrap();
+
+    c.create_chat(chat_args("gpt-4o", 10), None).await.unwrap();
+    match c.create_chat(chat_args("gpt-4o", 10), None).await {
+        Err(openai_rust::Error::RateLimited { model, retry_after }) => {
+            assert_eq!(model, "gpt-4o");
+            assert!(retry_after > Duration::from_secs(55));
+        }
+        res => panic!("expected a rate limit error, got {:?}", res),
+    }
+    // Models without limits are not affected.
+    c.create_chat(chat_args("gpt-4o-mini", 10), None)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+pub async fn token_estimates_are_reconciled_with_usage() {
+    let server = server().await;
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_rate_limiter(
+            RateLimiter::new()
+                .with_default_limits(Limits::new().with_tokens_per_minute(1000))
+                .with_on_limit(OnLimit::FailFast),
+        )
+        .build()
+        .unwrap();
+
+    // Each call reserves 906 tokens but only uses 11, so the budget is returned.
+    for _ in 0..3 {
+        c.create_chat(chat_args("gpt-4o", 900), None).await.unwrap();
+    }
+    let err = c
+        .create_chat(chat_args("gpt-4o", 1000), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, openai_rust::Error::RateLimited { .. }));
+}
+
+#[tokio::test]
+pub async fn estimates_above_the_limit_are_reconciled_with_what_was_taken() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(chat_completion_using(1500)))
+        .mount(&server)
+        .await;
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_rate_limiter(
+            RateLimiter::new()
+                .with_default_limits(Limits::new().with_tokens_per_minute(1000))
+                .with_on_limit(OnLimit::FailFast),
+        )
+        .build()
+        .unwrap();
+
+    // Only the 1000 tokens of a full budget are taken for the 5000 estimated,
+    // so using 1500 leaves the budget overdrawn rather than refunding 3500.
+    c.create_chat(chat_args("gpt-4o", 4994), None)
+        .await
+        .unwrap();
+    let err = c
+        .create_chat(chat_args("gpt-4o", 10), None)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, openai_rust::Error::RateLimited { .. }));
+}
+
+#[tokio::test]
+pub async fn waiting_calls_are_admitted_by_priority() {
+    let server = MockServer::start().await;
+    // The first call uses the whole budget, so the next ones have to wait for it to refill.
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(chat_completion_using(6000)))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .mount(&server)
+        .await;
+    let c = Arc::new(
+        openai_rust::Client::builder()
+            .with_base_url(format!("{}/v1", server.uri()))
+            .with_rate_limiter(
+                RateLimiter::new()
+                    .with_limits("gpt-4o", Limits::new().with_tokens_per_minute(6000)),
+            )
+            .build()
+            .unwrap(),
+    );
+    c.create_chat(chat_args("gpt-4o", 94), None).await.unwrap();
+
+    let finished = Arc::new(Mutex::new(Vec::new()));
+    let spawn = |priority: Priority, max_tokens: u32| {
+        let (c, finished) = (c.clone(), finished.clone());
+        tokio::spawn(async move {
+            priority
+                .scope(c.create_chat(chat_args("gpt-4o", max_tokens), None))
+                .await
+                .unwrap();
+            finished.lock().unwrap().push(priority);
+        })
+    };
+    // The bulk call fits after one second, but has to let the larger interactive call go first.
+    let bulk = spawn(Priority::Bulk, 94);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    let interactive = spawn(Priority::Interactive, 194);
+    bulk.await.unwrap();
+    interactive.await.unwrap();
+
+    assert_eq!(
+        *finished.lock().unwrap(),
+        [Priority::Interactive, Priority::Bulk]
+    );
+}
+
+#[tokio::test]
+pub async fn streams_are_reconciled_with_their_usage_chunk() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            "data: {\"id\":\"c\",\"created\":1,\"model\":\"gpt-4o\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi!\"},\"finish_reason\":\"stop\"}]}\n\n",
+            "data: {\"id\":\"c\",\"created\":1,\"model\":\"gpt-4o\",\"choices\":[],\"usage\":{\"prompt_tokens\":6,\"completion_tokens\":894,\"total_tokens\":900}}\n\n",
+            "data: [DONE]\n\n",
+        )))
+        .mount(&server)
+        .await;
+    let c = openai_rust::Client::builder()
+        .with_base_url(format!("{}/v1", server.uri()))
+        .with_rate_limiter(
+            RateLimiter::new()
+                .with_default_limits(Limits::new().with_tokens_per_minute(1000))
+                .with_on_limit(OnLimit::FailFast),
+        )
+        .build()
+        .unwrap();
+
+    // Estimated at only 6 tokens without max_tokens, the usage chunk charges the other 894.
+    let completion = c
+        .create_chat_stream(chat_args_with("gpt-4o", "Hello"), None)
+        .await
+        .unwrap()
+        .collect_completion()
+        .await
+        .unwrap();
+    assert_eq!(completion.usage.total_tokens, 900);
+    let res = c.create_chat_stream(chat_args("gpt-4o", 200), None).await;
+    assert!(matches!(res, Err(openai_rust::Error::RateLimited { .. })));
+}
+
+#[tokio::test]
+pub async fn huge_estimates_saturate() {
+    let server = server().await;
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let args = chat_args("gpt-4o", 0)
+        .with_max_completion_tokens(u32::MAX)
+        .with_n(2);
+    c.create_chat(args, None).await.unwrap();
+}
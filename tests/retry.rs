@@ -0,0 +1,204 @@
+use std::time::{Duration, Instant};
+
+use openai_rust::futures_util::StreamExt;
+use openai_rust::retry::RetryPolicy;
+use openai_rust2 as openai_rust;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args, CHAT_COMPLETION};
+
+/// Backoff long enough that a test only finishes quickly if the server's delay is honored.
+fn slow_policy() -> RetryPolicy {
+    RetryPolicy::default()
+        .with_base_delay(Duration::from_secs(30))
+        .with_max_delay(Duration::from_secs(60))
+}
+
+#[tokio::test]
+pub async fn retries_rate_limit_honoring_retry_after() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(429).insert_header("retry-after", "0"))
+        .up_to_n_times(2)
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c =
+        openai_rust::Client::new_with_base_url("", &server.uri()).with_retry_policy(slow_policy());
+    let start = Instant::now();
+    let res = c.create_chat(chat_args(), None).await.unwrap();
+    assert_eq!(res.to_string(), "Hi!");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+pub async fn honors_ratelimit_reset_headers() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(429)
+                .insert_header("x-ratelimit-remaining-tokens", "0")
+                .insert_header("x-ratelimit-reset-tokens", "20ms")
+                .insert_header("x-ratelimit-reset-requests", "6m0s"),
+        )
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .mount(&server)
+        .await;
+
+    let c =
+        openai_rust::Client::new_with_base_url("", &server.uri()).with_retry_policy(slow_policy());
+    let start = Instant::now();
+    c.create_chat(chat_args(), None).await.unwrap();
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+pub async fn gives_up_after_max_attempts_and_skips_client_errors() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(503).insert_header("retry-after", "0"))
+        .expect(3)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/embeddings"))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let err = c.create_chat(chat_args(), None).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(503));
+
+    let args = openai_rust::embeddings::EmbeddingsArguments::new(
+        "text-embedding-ada-002",
+        "The food was delicious and the waiter...".to_owned(),
+    );
+    let err = c.create_embeddings(args, None).await.unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(400));
+}
+
+#[tokio::test]
+pub async fn retries_stream_before_first_byte() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(502).insert_header("retry-after-ms", "10"))
+        .up_to_n_times(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            "data: {\"id\":\"chatcmpl-1\",\"created\":1700000000,\"model\":\"gpt-3.5-turbo\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"Hi!\"},\"finish_reason\":null}]}\n\n",
+        ))
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let chunks = c
+        .create_chat_stream(chat_args(), None)
+        .await
+        .unwrap()
+        .collect::<Vec<_>>()
+        .await;
+    assert_eq!(chunks.len(), 1);
+    assert_eq!(chunks[0].as_ref().unwrap().to_string(), "Hi!");
+}
+
+#[tokio::test]
+pub async fn out_of_range_delays_fall_back_to_backoff() {
+    let server = MockServer::start().await;
+    for (name, value) in [
+        ("retry-after", "1e400"),
+        ("retry-after-ms", "1e30"),
+        ("x-ratelimit-reset-tokens", "inf"),
+    ] {
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(429).insert_header(name, value))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+    }
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let c = openai_rust::Client::new_with_base_url("", &server.uri()).with_retry_policy(
+        RetryPolicy::default()
+            .with_max_attempts(4)
+            .with_base_delay(Duration::from_millis(10)),
+    );
+    let start = Instant::now();
+    let res = c.create_chat(chat_args(), None).await.unwrap();
+    assert_eq!(res.to_string(), "Hi!");
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+pub async fn out_of_range_fields_are_clamped() {
+    for jitter in [1.5, -1.0, f64::NAN] {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(CHAT_COMPLETION))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let c = openai_rust::Client::new_with_base_url("", &server.uri()).with_retry_policy(
+            RetryPolicy {
+                jitter,
+                base_delay: Duration::from_millis(10),
+                ..Default::default()
+            },
+        );
+        let res = c.create_chat(chat_args(), None).await.unwrap();
+        assert_eq!(res.to_string(), "Hi!");
+    }
+
+    // No attempts at all is still one attempt.
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let c =
+        openai_rust::Client::new_with_base_url("", &server.uri()).with_retry_policy(RetryPolicy {
+            max_attempts: 0,
+            ..Default::default()
+        });
+    assert!(c.create_chat(chat_args(), None).await.is_err());
+}
@@ -0,0 +1,195 @@
+#![cfg(feature = "schemars")]
+use openai_rust::chat::{strict_schema, Tool, ToolCall};
+use openai_rust2 as openai_rust;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(JsonSchema, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Unit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(JsonSchema, Deserialize, Debug, PartialEq)]
+struct Place {
+    city: String,
+    country: Option<String>,
+}
+
+/// Look up a forecast.
+#[derive(JsonSchema, Deserialize, Debug, PartialEq)]
+struct Forecast {
+    place: Place,
+    days: u8,
+    #[serde(default)]
+    unit: Option<Unit>,
+    tags: Vec<String>,
+}
+
+#[test]
+pub fn schemas_are_strict() {
+    let schema = strict_schema::<Forecast>();
+    assert_eq!(schema["type"], "object");
+    assert_eq!(schema["description"], "Look up a forecast.");
+    assert_eq!(schema["required"], json!(["days", "place", "tags", "unit"]));
+    assert_eq!(schema["additionalProperties"], false);
+    assert!(schema.get("$schema").is_none());
+    assert!(schema["properties"]["days"].get("format").is_none());
+    assert!(schema["properties"]["unit"].get("default").is_none());
+
+    let place = &schema["$defs"]["Place"];
+    assert_eq!(place["required"], json!(["city", "country"]));
+    assert_eq!(place["additionalProperties"], false);
+    assert_eq!(
+        place["properties"]["country"]["type"],
+        json!(["string", "null"])
+    );
+
+    let Tool::Function(tool) = Tool::typed::<Forecast>("forecast", "Get a forecast.") else {
+        panic!("not a function tool");
+    };
+    assert_eq!(tool.function.strict, Some(true));
+    assert_eq!(tool.function.parameters, Some(schema));
+}
+
+#[test]
+pub fn typed_arguments() {
+    let call: ToolCall = serde_json::from_value(json!({
+        "id": "call_1", "type": "function",
+        "function": {"name": "forecast", "arguments": r#"{"place":{"city":"Oslo","country":null},"days":3,"unit":"celsius","tags":[]}"#},
+    }))
+    .unwrap();
+    assert_eq!(
+        call.parse_arguments::<Forecast>().unwrap(),
+        Forecast {
+            place: Place {
+                city: "Oslo".to_owned(),
+                country: None
+            },
+            days: 3,
+            unit: Some(Unit::Celsius),
+            tags: vec![],
+        }
+    );
+    let mut bad = call.clone();
+    bad.function.arguments = r#"{"days":"3"}"#.to_owned();
+    assert!(matches!(
+        bad.parse_arguments::<Forecast>(),
+        Err(openai_rust::Error::Decode { .. })
+    ));
+}
+
+#[tokio::test]
+pub async fn typed_local_tools() {
+    use openai_rust::chat::{ChatArguments, LocalTool, ToolOutcome, ToolRunner};
+    use openai_rust::middleware::{from_fn, Response};
+
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(|req, _| {
+            Box::pin(async move {
+                let body = req.json::<serde_json::Value>()?.unwrap();
+                assert_eq!(body["tools"][0]["function"]["strict"], true);
+                let message = match body["messages"].as_array().unwrap().len() {
+                    0 => json!({"role": "assistant", "content": null, "tool_calls": [
+                        {"id": "1", "type": "function", "function": {"name": "place", "arguments": r#"{"city":"Oslo","country":null}"#}},
+                        {"id": "2", "type": "function", "function": {"name": "place", "arguments": r#"{"city":1,"country":null}"#}},
+                    ]}),
+                    _ => json!({"role": "assistant", "content": "Done."}),
+                };
+                let completion = json!({
+                    "id": "c", "created": 1, "model": "m",
+                    "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+                });
+                Ok(Response::new(200, completion.to_string()))
+            })
+        }))
+        .build()
+        .unwrap();
+    let runner = ToolRunner::new().with_tool(LocalTool::typed(
+        "place",
+        "Describe a place.",
+        |place: Place| async move { Ok::<_, String>(place.city) },
+    ));
+    let run = runner
+        .run(&c, ChatArguments::new("m", vec![]))
+        .await
+        .unwrap();
+    let outcomes: Vec<_> = run.steps[0].calls.iter().map(|r| &r.outcome).collect();
+    assert_eq!(outcomes[0], &ToolOutcome::Ok(json!("Oslo")));
+    assert!(matches!(outcomes[1], ToolOutcome::InvalidArguments(_)));
+}
+
+#[tokio::test]
+pub async fn chat_parsed_requests_the_schema_of_the_type() {
+    use openai_rust::chat::{ChatArguments, Parsed};
+    use openai_rust::middleware::{from_fn, Response};
+
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(|req, _| {
+            Box::pin(async move {
+                let body = req.json::<serde_json::Value>()?.unwrap();
+                let format = &body["response_format"];
+                assert_eq!(format["type"], "json_schema");
+                assert_eq!(format["json_schema"]["name"], "Place");
+                assert_eq!(format["json_schema"]["strict"], true);
+                assert_eq!(format["json_schema"]["schema"], strict_schema::<Place>());
+                let completion = json!({
+                    "id": "c", "created": 1, "model": "m",
+                    "choices": [{"index": 0, "finish_reason": "stop", "message": {
+                        "role": "assistant", "content": r#"{"city":"Oslo","country":"Norway"}"#,
+                    }}],
+                    "usage": {"prompt_tokens": 5, "completion_tokens": 7, "total_tokens": 12},
+                });
+                Ok(Response::new(200, completion.to_string()))
+            })
+        }))
+        .build()
+        .unwrap();
+    let res = c
+        .create_chat_parsed::<Place>(ChatArguments::new("m", vec![]), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.parsed,
+        Parsed::Value(Place {
+            city: "Oslo".to_owned(),
+            country: Some("Norway".to_owned())
+        })
+    );
+    assert_eq!(res.completion.usage.total_tokens, 12);
+}
+
+#[derive(JsonSchema, Deserialize)]
+struct Labeled {
+    name: String,
+    labels: std::collections::HashMap<String, u32>,
+}
+
+#[test]
+pub fn maps_are_kept_and_turn_strict_mode_off() {
+    use openai_rust::chat::ResponseFormat;
+
+    let schema = strict_schema::<Labeled>();
+    assert_eq!(schema["required"], json!(["labels", "name"]));
+    assert_eq!(schema["additionalProperties"], false);
+    let labels = &schema["properties"]["labels"];
+    assert_eq!(labels["type"], "object");
+    assert_eq!(labels["additionalProperties"]["type"], "integer");
+    assert!(labels.get("properties").is_none());
+
+    let Tool::Function(tool) = Tool::typed::<Labeled>("label", "Label a thing.") else {
+        panic!("not a function tool");
+    };
+    assert_eq!(tool.function.strict, Some(false));
+    let ResponseFormat::JsonSchema { json_schema } = ResponseFormat::typed::<Labeled>() else {
+        panic!("not a JSON schema format");
+    };
+    assert_eq!(json_schema.strict, Some(false));
+    assert_eq!(json_schema.schema, schema);
+
+    let labeled: Labeled =
+        serde_json::from_value(json!({"name": "a", "labels": {"x": 1}})).unwrap();
+    assert_eq!((labeled.name.as_str(), labeled.labels["x"]), ("a", 1));
+}
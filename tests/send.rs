@@ -0,0 +1,46 @@
+//! Compile-time checks that streams and call futures can be spawned onto a multi-threaded runtime.
+use openai_rust::chat::stream::{ChatCompletionChunkStream, ChoiceStream};
+use openai_rust::middleware::{Body, Response};
+use openai_rust2 as openai_rust;
+
+fn assert_send<T: Send>(_: &T) {}
+fn assert_send_unpin<T: Send + Unpin>() {}
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+pub fn streams_are_send_and_unpin() {
+    assert_send_unpin::<ChatCompletionChunkStream>();
+    assert_send_unpin::<ChoiceStream>();
+    assert_send_unpin::<Body>();
+    assert_send_unpin::<Response>();
+    assert_send_sync::<openai_rust::Client>();
+}
+
+#[test]
+pub fn call_futures_are_send() {
+    let c = openai_rust::Client::new("");
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o", vec![]);
+    assert_send(&c.create_chat(args.clone(), None));
+    assert_send(&c.create_chat_stream(args, None));
+    assert_send(&c.list_models(None));
+    assert_send(&c.create_embeddings(
+        openai_rust::embeddings::EmbeddingsArguments::new("text-embedding-3-small", String::new()),
+        None,
+    ));
+    assert_send(&openai_rust::meta::WithMeta::capture(c.list_models(None)));
+}
+
+#[tokio::test]
+pub async fn streams_can_be_consumed_in_spawned_tasks() {
+    use openai_rust::futures_util::StreamExt;
+
+    let c = openai_rust::Client::new_with_base_url("", "http://127.0.0.1:9")
+        .with_retry_policy(openai_rust::retry::RetryPolicy::none());
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o", vec![]);
+    let handle = tokio::spawn(async move {
+        if let Ok(mut stream) = c.create_chat_stream(args, None).await {
+            while stream.next().await.is_some() {}
+        }
+    });
+    handle.await.unwrap();
+}
@@ -0,0 +1,347 @@
+use openai_rust::chat::stream::{
+    ChatCompletionChunk, ChatCompletionChunkStream, ChatStreamAccumulator,
+};
+use openai_rust::futures_util::{stream, StreamExt};
+use openai_rust::middleware::{from_fn, Body, Response};
+use openai_rust::sse::{Decoder, Event};
+use openai_rust2 as openai_rust;
+
+/// Synthetic streams in `tests/fixtures/sse`, written by hand in the frame format of each server
+/// rather than captured from it, with the text they decode to. CRLF line endings, comments and
+/// `event:`/`id:` fields are covered by the inline streams below.
+const FIXTURES: [(&str, &str); 4] = [
+    ("openai", "Un café ☕ s'il vous plaît."),
+    ("xai", "The answer is 42."),
+    ("ollama", "Hello! How can I help?"),
+    ("vllm", "Grüß Gott! 👋"),
+];
+
+fn fixture(name: &str) -> String {
+    let path = format!(
+        "{}/tests/fixtures/sse/synthetic_{}.txt",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    std::fs::read_to_string(path).unwrap()
+}
+
+/// Stream `body` through `create_chat_stream`, one byte per network chunk if `split`.
+async fn chat_stream(body: String, split: bool) -> Vec<openai_rust::Result<ChatCompletionChunk>> {
+    chunk_stream(body, split).await.collect().await
+}
+
+async fn chunk_stream(body: String, split: bool) -> ChatCompletionChunkStream {
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(move |_, _| {
+            let body = body.clone();
+            Box::pin(async move {
+                let mut res = Response::new(200, body.clone());
+                if split {
+                    let bytes = body.into_bytes().into_iter().map(|b| Ok(vec![b].into()));
+                    res.body = Body::Stream(stream::iter(bytes).boxed());
+                }
+                Ok(res)
+            })
+        }))
+        .build()
+        .unwrap();
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o-mini", vec![]);
+    c.create_chat_stream(args, None).await.unwrap()
+}
+
+/// `data` frames for `deltas` of `(index, content, finish_reason)`.
+fn sse_body(deltas: &[(u32, &str, Option<&str>)]) -> String {
+    deltas
+        .iter()
+        .map(|(index, content, finish_reason)| {
+            let data = serde_json::json!({
+                "id": "c", "created": 1, "model": "m",
+                "choices": [{"index": index, "delta": {"content": content}, "finish_reason": finish_reason}],
+            });
+            format!("data: {}\n\n", data)
+        })
+        .collect::<String>()
+        + "data: [DONE]\n\n"
+}
+
+fn decode_all(decoder: &mut Decoder) -> Vec<Event> {
+    let mut events = std::iter::from_fn(|| decoder.next_event()).collect::<Vec<_>>();
+    events.extend(decoder.finish());
+    events
+}
+
+#[tokio::test]
+pub async fn synthetic_streams_decode_in_any_chunking() {
+    for (name, text) in FIXTURES {
+        for split in [false, true] {
+            let content = chat_stream(fixture(name), split)
+                .await
+                .into_iter()
+                .map(|chunk| chunk.unwrap().to_string())
+                .collect::<String>();
+            assert_eq!(content, text, "{} (split: {})", name, split);
+        }
+    }
+}
+
+#[test]
+pub fn fields_comments_and_line_endings() {
+    let mut decoder = Decoder::new();
+    decoder.push(
+        b"\xEF\xBB\xBF: keep-alive\r\n\
+          retry: 3000\r\n\
+          event: delta\rid: 7\ndata:first\r\n\
+          data: second\r\n\
+          \r\n\
+          data\n\n\
+          event: ignored\n\n\
+          data: unterminated",
+    );
+    assert_eq!(
+        decode_all(&mut decoder),
+        [
+            Event {
+                event: Some("delta".to_owned()),
+                data: "first\nsecond".to_owned(),
+                id: Some("7".to_owned()),
+            },
+            Event {
+                event: None,
+                data: String::new(),
+                id: Some("7".to_owned()),
+            },
+            Event {
+                event: None,
+                data: "unterminated".to_owned(),
+                id: Some("7".to_owned()),
+            },
+        ]
+    );
+    assert_eq!(decoder.retry(), Some(std::time::Duration::from_secs(3)));
+}
+
+#[test]
+pub fn crlf_split_between_chunks_is_one_line_ending() {
+    let mut decoder = Decoder::new();
+    decoder.push(b"data: a\r");
+    assert_eq!(decoder.next_event(), None);
+    decoder.push(b"\n\r");
+    assert_eq!(decoder.next_event(), None);
+    decoder.push(b"\ndata: b\n\n");
+    let data = decode_all(&mut decoder)
+        .into_iter()
+        .map(|e| e.data)
+        .collect::<Vec<_>>();
+    assert_eq!(data, ["a", "b"]);
+}
+
+#[tokio::test]
+pub async fn error_frames_end_the_stream_with_an_api_error() {
+    let body = format!(
+        "{}data: {{\"error\":{{\"message\":\"The server had an error while processing your request.\",\"type\":\"server_error\",\"code\":null,\"param\":null}}}}\n\ndata: [DONE]\n\n",
+        fixture("ollama").lines().next().unwrap().to_owned() + "\n\n"
+    );
+    let chunks = chat_stream(body, false).await;
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0].as_ref().unwrap().to_string(), "Hello");
+    match &chunks[1] {
+        Err(openai_rust::Error::Api(e)) => {
+            assert_eq!(e.error_type(), Some("server_error"));
+            assert_eq!(
+                e.message(),
+                Some("The server had an error while processing your request.")
+            );
+        }
+        res => panic!("expected an API error, got {:?}", res),
+    }
+}
+
+#[tokio::test]
+pub async fn accumulator_folds_choices_by_index() {
+    let body = [
+        r#"{"id":"c1","created":1,"model":"m","choices":[{"index":1,"delta":{"content":"B"},"finish_reason":null}]}"#,
+        r#"{"id":"c1","created":1,"model":"m","choices":[{"index":0,"delta":{"content":"A"},"finish_reason":null}]}"#,
+        r#"{"id":"c1","created":1,"model":"m","choices":[{"index":0,"delta":{"content":"a"},"finish_reason":"stop"},{"index":1,"delta":{"content":"b"},"finish_reason":null}]}"#,
+        r#"{"id":"c1","created":1,"model":"m","choices":[{"index":1,"delta":{},"finish_reason":"length"}]}"#,
+    ]
+    .iter()
+    .map(|data| format!("data: {}\n\n", data))
+    .collect::<String>()
+        + "data: [DONE]\n\n";
+
+    let mut accumulator = ChatStreamAccumulator::new();
+    for chunk in chat_stream(body, false).await {
+        accumulator.push(&chunk.unwrap());
+    }
+    let completion = accumulator.finish();
+    assert_eq!(completion.id.as_deref(), Some("c1"));
+    assert_eq!(completion.model.as_deref(), Some("m"));
+    let choices = completion
+        .choices
+        .iter()
+        .map(|c| {
+            (
+                c.index,
+                c.message.content.as_ref().unwrap().as_text().unwrap(),
+                c.finish_reason.as_str(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        choices,
+        [(Some(0), "Aa", "stop"), (Some(1), "Bb", "length")]
+    );
+}
+
+#[tokio::test]
+pub async fn accumulated_fixtures_match_streamed_text() {
+    for (name, text) in FIXTURES {
+        let mut accumulator = ChatStreamAccumulator::new();
+        for chunk in chat_stream(fixture(name), true).await {
+            accumulator.push(&chunk.unwrap());
+        }
+        let completion = accumulator.finish();
+        assert_eq!(completion.to_string(), text, "{}", name);
+        assert_eq!(
+            completion.choices[0].message.role,
+            openai_rust::chat::Role::Assistant
+        );
+    }
+}
+
+#[tokio::test]
+pub async fn full_deltas_and_usage_chunk() {
+    let body = [
+        r#"{"id":"c","created":1,"model":"m","choices":[{"index":0,"delta":{"role":"assistant","content":"","reasoning_content":"Think."},"logprobs":{"content":[]},"finish_reason":null}]}"#,
+        r#"{"id":"c","created":1,"model":"m","choices":[{"index":0,"delta":{"content":"Hi"},"logprobs":{"content":[{"token":"Hi","logprob":-0.5,"bytes":[72,105],"top_logprobs":[{"token":"Hi","logprob":-0.5,"bytes":[72,105]}]}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","created":1,"model":"m","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"f","arguments":""}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","created":1,"model":"m","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{}"}}]},"finish_reason":null}]}"#,
+        r#"{"id":"c","created":1,"model":"m","choices":[{"index":0,"delta":{"refusal":"No."},"finish_reason":"tool_calls"}]}"#,
+        r#"{"id":"c","created":1,"model":"m","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":4,"total_tokens":7}}"#,
+    ]
+    .iter()
+    .map(|data| format!("data: {}\n\n", data))
+    .collect::<String>()
+        + "data: [DONE]\n\n";
+
+    let chunks = chat_stream(body, true)
+        .await
+        .into_iter()
+        .collect::<openai_rust::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(chunks.len(), 6);
+    let delta = |i: usize| &chunks[i].choices[0].delta;
+    assert_eq!(delta(0).role, Some(openai_rust::chat::Role::Assistant));
+    assert_eq!(delta(0).reasoning_content.as_deref(), Some("Think."));
+    let call = &delta(2).tool_calls.as_ref().unwrap()[0];
+    assert_eq!(call.id.as_deref(), Some("call_1"));
+    assert_eq!(call.function.as_ref().unwrap().name.as_deref(), Some("f"));
+    assert_eq!(delta(4).refusal.as_deref(), Some("No."));
+    assert_eq!(chunks[5].to_string(), "");
+    assert_eq!(chunks[5].usage.as_ref().unwrap().total_tokens, 7);
+
+    let mut accumulator = ChatStreamAccumulator::new();
+    chunks.iter().for_each(|chunk| accumulator.push(chunk));
+    let completion = accumulator.finish();
+    assert_eq!(completion.usage.total_tokens, 7);
+    let message = &completion.choices[0].message;
+    let calls = message.tool_calls.as_ref().unwrap();
+    assert_eq!(calls.len(), 1);
+    assert_eq!(calls[0].id, "call_1");
+    assert_eq!(calls[0].function.name, "f");
+    assert_eq!(calls[0].function.arguments, "{}");
+    assert_eq!(completion.to_string(), "Hi");
+    assert_eq!(message.refusal.as_deref(), Some("No."));
+    let tokens = completion.choices[0].logprobs.as_ref().unwrap();
+    let tokens = tokens.content.as_ref().unwrap();
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].bytes.as_deref(), Some(&b"Hi"[..]));
+}
+
+#[test]
+pub fn stream_options_are_serialized() {
+    let args = openai_rust::chat::ChatArguments::new("m", vec![]).with_stream_options(
+        openai_rust::chat::StreamOptions {
+            include_usage: true,
+        },
+    );
+    let json = serde_json::to_value(&args).unwrap();
+    assert_eq!(json["stream_options"]["include_usage"], true);
+    let json = serde_json::to_value(openai_rust::chat::ChatArguments::new("m", vec![])).unwrap();
+    assert!(json.get("stream_options").is_none());
+}
+
+#[tokio::test]
+pub async fn stream_options_are_only_sent_when_streaming() {
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(|req, _| {
+            Box::pin(async move {
+                let body = req.json::<serde_json::Value>()?.unwrap();
+                assert!(body.get("stream").is_none());
+                assert!(body.get("stream_options").is_none());
+                let completion = serde_json::json!({
+                    "id": "c", "created": 1, "model": "m",
+                    "choices": [{"index": 0, "message": {"role": "assistant", "content": "Hi"}, "finish_reason": "stop"}],
+                });
+                Ok(Response::new(200, completion.to_string()))
+            })
+        }))
+        .build()
+        .unwrap();
+    let args = openai_rust::chat::ChatArguments::new("m", vec![]).with_stream_options(
+        openai_rust::chat::StreamOptions {
+            include_usage: true,
+        },
+    );
+    assert_eq!(c.create_chat(args, None).await.unwrap().to_string(), "Hi");
+}
+
+const INTERLEAVED: &[(u32, &str, Option<&str>)] = &[
+    (0, "A", None),
+    (2, "C", None),
+    (1, "B", None),
+    (2, "c", Some("stop")),
+    (0, "a", None),
+    (1, "b", None),
+    (0, "!", Some("stop")),
+    (1, "", Some("length")),
+];
+
+#[tokio::test]
+pub async fn split_choices_concurrently_and_in_turn() {
+    let streams = chunk_stream(sse_body(INTERLEAVED), true)
+        .await
+        .split_choices(3);
+    let texts = openai_rust::futures_util::future::join_all(
+        streams
+            .into_iter()
+            .map(|s| s.map(Result::unwrap).collect::<String>()),
+    )
+    .await;
+    assert_eq!(texts, ["Aa!", "Bb", "Cc"]);
+
+    let mut streams = chunk_stream(sse_body(INTERLEAVED), false)
+        .await
+        .split_choices(3);
+    assert_eq!(streams[2].index(), 2);
+    let mut texts = vec![];
+    for stream in streams.iter_mut().rev() {
+        texts.push(stream.map(Result::unwrap).collect::<String>().await);
+    }
+    assert_eq!(texts, ["Cc", "Bb", "Aa!"]);
+}
+
+#[tokio::test]
+pub async fn choice_deltas_keep_their_index() {
+    let deltas = chunk_stream(sse_body(INTERLEAVED), false)
+        .await
+        .choice_deltas()
+        .map(|delta| {
+            let (index, delta) = delta.unwrap();
+            (index, delta.content.unwrap())
+        })
+        .collect::<Vec<_>>()
+        .await;
+    let expected = INTERLEAVED.iter().map(|(i, c, _)| (*i, c.to_string()));
+    assert_eq!(deltas, expected.collect::<Vec<_>>());
+}
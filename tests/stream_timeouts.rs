@@ -0,0 +1,138 @@
+use std::time::Duration;
+
+use openai_rust::chat::stream::{ChatCompletionChunkStream, StreamDeadline, StreamTimeouts};
+use openai_rust::futures_util::{stream, StreamExt};
+use openai_rust::middleware::{from_fn, Body, Response};
+use openai_rust::rate_limit::{Limits, RateLimiter};
+use openai_rust2 as openai_rust;
+
+fn frame(content: &str) -> openai_rust::Result<bytes::Bytes> {
+    let data = serde_json::json!({
+        "id": "c", "created": 1, "model": "m",
+        "choices": [{"index": 0, "delta": {"content": content}, "finish_reason": null}],
+    });
+    Ok(format!("data: {}\n\n", data).into())
+}
+
+/// A stream whose body sends a role-only frame if `role`, then `frames` frames `every` apart,
+/// then stalls.
+async fn stalling_stream(
+    timeouts: StreamTimeouts,
+    role: bool,
+    frames: usize,
+    every: Duration,
+) -> ChatCompletionChunkStream {
+    let c = openai_rust::Client::builder()
+        .with_stream_timeouts(timeouts)
+        .with_middleware(from_fn(move |_, _| {
+            Box::pin(async move {
+                let mut res = Response::new(200, "");
+                let role_frame = serde_json::json!({
+                    "id": "c", "created": 1, "model": "m",
+                    "choices": [{"index": 0, "delta": {"role": "assistant"}, "finish_reason": null}],
+                });
+                let first = stream::iter(role.then(|| Ok(format!("data: {}\n\n", role_frame).into())));
+                let rest = stream::iter(0..frames).then(move |i| async move {
+                    tokio::time::sleep(every).await;
+                    frame(&i.to_string())
+                });
+                res.body = Body::Stream(first.chain(rest).chain(stream::pending()).boxed());
+                Ok(res)
+            })
+        }))
+        .build()
+        .unwrap();
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o-mini", vec![]);
+    c.create_chat_stream(args, None).await.unwrap()
+}
+
+fn deadline(res: Option<openai_rust::Result<impl std::fmt::Debug>>) -> StreamDeadline {
+    match res {
+        Some(Err(openai_rust::Error::StreamTimeout { deadline, .. })) => deadline,
+        other => panic!("expected a stream timeout, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+pub async fn idle_gap_between_chunks() {
+    let timeouts = StreamTimeouts::new().with_idle(Duration::from_millis(100));
+    let mut s = stalling_stream(timeouts, true, 2, Duration::from_millis(10)).await;
+    for _ in 0..3 {
+        s.next().await.unwrap().unwrap();
+    }
+    assert_eq!(deadline(s.next().await), StreamDeadline::Idle);
+    assert!(s.next().await.is_none());
+}
+
+#[tokio::test]
+pub async fn first_chunk_deadline_can_be_set_per_stream() {
+    let mut s = stalling_stream(StreamTimeouts::new(), false, 0, Duration::ZERO)
+        .await
+        .with_timeouts(StreamTimeouts::new().with_first_chunk(Duration::from_millis(100)));
+    assert_eq!(deadline(s.next().await), StreamDeadline::FirstChunk);
+    assert_eq!(s.stats().chunks, 0);
+}
+
+#[tokio::test]
+pub async fn total_deadline_and_stats() {
+    let timeouts = StreamTimeouts::new()
+        .with_idle(Duration::from_secs(5))
+        .with_total(Duration::from_millis(300));
+    let mut s = stalling_stream(timeouts, true, usize::MAX, Duration::from_millis(20)).await;
+    let mut chunks = 0;
+    let error = loop {
+        match s.next().await.unwrap() {
+            Ok(_) => chunks += 1,
+            Err(e) => break e,
+        }
+    };
+    assert!(matches!(
+        error,
+        openai_rust::Error::StreamTimeout {
+            deadline: StreamDeadline::Total,
+            ..
+        }
+    ));
+
+    let stats = s.stats();
+    assert_eq!(stats.chunks, chunks);
+    assert!(stats.time_to_first_chunk.unwrap() < stats.time_to_first_token.unwrap());
+    assert!(stats.elapsed >= Duration::from_millis(300));
+    assert!(stats.chunks_per_second() > 10.0, "{:?}", stats);
+}
+
+#[tokio::test]
+pub async fn stalled_response_headers_miss_the_first_chunk_deadline() {
+    let c = openai_rust::Client::builder()
+        .with_stream_timeouts(StreamTimeouts::new().with_first_chunk(Duration::from_millis(100)))
+        .with_middleware(from_fn(|_, _| Box::pin(std::future::pending())))
+        .build()
+        .unwrap();
+    let args = openai_rust::chat::ChatArguments::new("gpt-4o-mini", vec![]);
+    let res = c.create_chat_stream(args, None).await;
+    assert_eq!(deadline(Some(res.map(|_| ()))), StreamDeadline::FirstChunk);
+}
+
+#[tokio::test]
+pub async fn time_queued_for_the_rate_limiter_does_not_count() {
+    let c = openai_rust::Client::builder()
+        .with_stream_timeouts(StreamTimeouts::new().with_first_chunk(Duration::from_millis(100)))
+        .with_rate_limiter(
+            RateLimiter::new().with_default_limits(Limits::new().with_tokens_per_minute(6000)),
+        )
+        .with_middleware(from_fn(|_, _| {
+            Box::pin(async { Ok(Response::new(200, frame("Hi")?)) })
+        }))
+        .build()
+        .unwrap();
+    let args = |max_tokens| {
+        openai_rust::chat::ChatArguments::new("gpt-4o-mini", vec![]).with_max_tokens(max_tokens)
+    };
+    // Takes the whole budget, which refills at 100 tokens per second.
+    c.create_chat_stream(args(6000), None).await.unwrap();
+
+    let start = std::time::Instant::now();
+    let mut s = c.create_chat_stream(args(50), None).await.unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(300));
+    assert_eq!(s.next().await.unwrap().unwrap().to_string(), "Hi");
+}
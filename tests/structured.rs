@@ -0,0 +1,62 @@
+use openai_rust::chat::{ChatCompletion, Parsed, ResponseFormat};
+use openai_rust2 as openai_rust;
+use serde::Deserialize;
+use serde_json::json;
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Event {
+    name: String,
+}
+
+fn completion(message: serde_json::Value) -> ChatCompletion {
+    serde_json::from_value(json!({
+        "id": "c", "created": 1, "model": "m",
+        "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+    }))
+    .unwrap()
+}
+
+#[test]
+pub fn response_formats_are_serialized() {
+    assert_eq!(
+        serde_json::to_value(ResponseFormat::Text).unwrap(),
+        json!({"type": "text"})
+    );
+    assert_eq!(
+        serde_json::to_value(ResponseFormat::JsonObject).unwrap(),
+        json!({"type": "json_object"})
+    );
+    let schema =
+        json!({"type": "object", "properties": {}, "required": [], "additionalProperties": false});
+    assert_eq!(
+        serde_json::to_value(ResponseFormat::json_schema("event", schema.clone())).unwrap(),
+        json!({"type": "json_schema", "json_schema": {"name": "event", "schema": schema, "strict": true}})
+    );
+}
+
+#[test]
+pub fn answers_and_refusals_are_parsed() {
+    let answer = completion(json!({"role": "assistant", "content": r#"{"name":"Launch"}"#}));
+    assert_eq!(
+        answer.parse::<Event>().unwrap(),
+        Parsed::Value(Event {
+            name: "Launch".to_owned()
+        })
+    );
+
+    let refusal = completion(
+        json!({"role": "assistant", "content": null, "refusal": "I can't help with that."}),
+    );
+    let parsed = refusal.parse::<Event>().unwrap();
+    assert_eq!(
+        parsed,
+        Parsed::Refusal("I can't help with that.".to_owned())
+    );
+    assert_eq!(parsed.value(), None);
+
+    let truncated = completion(json!({"role": "assistant", "content": r#"{"name":"Lau"#}));
+    assert!(matches!(
+        truncated.parse::<Event>(),
+        Err(openai_rust::Error::Decode { .. })
+    ));
+}
@@ -1,30 +1,64 @@
+//! End-to-end tests that replay the cassettes in `tests/cassettes`.
+//!
+//! The checked-in cassettes are synthetic fixtures written by hand in the shape of API responses,
+//! not recordings: their ids, dates and headers are made up and the header set is minimal.
+//! They run offline by default. To replace them with real recordings of the live API, run
+//! `OPENAI_RECORD=1 OPENAI_API_KEY=sk-... cargo test --test test`
+//! (`list_models` also expects Ollama on `localhost:11434`).
 use base64::{engine::general_purpose, Engine};
 use futures_util::StreamExt;
 use lazy_static::lazy_static;
+use openai_rust::cassette::{Cassette, Mode};
 use openai_rust2 as openai_rust;
 use std::env::var;
 use std::fs::File;
 use std::io::Write;
 
 lazy_static! {
-    static ref KEY: String = var("OPENAI_API_KEY").unwrap_or_else(|e| {
-        panic!("Please set the OPENAI_API_KEY environment variable");
-    });
+    static ref MODE: Mode = match var("OPENAI_RECORD") {
+        Ok(_) => Mode::Record,
+        Err(_) => Mode::Replay,
+    };
+    static ref KEY: String = match *MODE {
+        Mode::Record => var("OPENAI_API_KEY").unwrap_or_else(|_| {
+            panic!("Please set the OPENAI_API_KEY environment variable to record cassettes");
+        }),
+        Mode::Replay => String::new(),
+    };
+}
+
+/// A client builder that records to or replays from `tests/cassettes/{name}.json`.
+fn client(name: &str) -> openai_rust::ClientBuilder {
+    let path = format!(
+        "{}/tests/cassettes/{}.json",
+        env!("CARGO_MANIFEST_DIR"),
+        name
+    );
+    openai_rust::Client::builder()
+        .with_api_key(KEY.as_str())
+        .with_cassette(Cassette::new(path, *MODE).unwrap())
 }
 
 #[tokio::test]
 pub async fn list_models() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("list_models").build().unwrap();
     let models_vec = c.list_models(None).await.unwrap();
-    assert!(models_vec.len() > 0);
+    assert!(!models_vec.is_empty());
 
-    let c_openai_manually = openai_rust::Client::new_with_base_url(&KEY, "https://api.openai.com");
+    let c_openai_manually = client("list_models_base_url_without_path")
+        .with_base_url("https://api.openai.com")
+        .build()
+        .unwrap();
     let models_vec = c_openai_manually.list_models(None).await.unwrap();
-    assert!(models_vec.len() > 0);
+    assert!(!models_vec.is_empty());
 
-    let c_local_ollama = openai_rust::Client::new_with_base_url("", "http://localhost:11434");
+    let c_local_ollama = client("list_models_ollama")
+        .with_api_key("")
+        .with_base_url("http://localhost:11434")
+        .build()
+        .unwrap();
     let models_vec = c_local_ollama.list_models(None).await.unwrap();
-    assert!(models_vec.len() > 0);
+    assert!(!models_vec.is_empty());
     models_vec.iter().for_each(|m| {
         println!("Local Ollama Model: {}", m.id);
     });
@@ -32,38 +66,35 @@ pub async fn list_models() {
 
 #[tokio::test]
 pub async fn create_chat() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_chat").build().unwrap();
     let args = openai_rust::chat::ChatArguments::new(
         "gpt-3.5-turbo",
-        vec![openai_rust::chat::Message {
-            role: "user".to_owned(),
-            content: "Hello GPT!".to_owned(),
-        }],
+        vec![openai_rust::chat::Message::user("Hello GPT!")],
     );
     c.create_chat(args, None).await.unwrap();
 }
 
 #[tokio::test]
 pub async fn create_chat_stream() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_chat_stream").build().unwrap();
     let args = openai_rust::chat::ChatArguments::new(
         "gpt-3.5-turbo",
-        vec![openai_rust::chat::Message {
-            role: "user".to_owned(),
-            content: "Hello GPT!".to_owned(),
-        }],
+        vec![openai_rust::chat::Message::user("Hello GPT!")],
     );
 
-    c.create_chat_stream(args, None)
+    let chunks = c
+        .create_chat_stream(args, None)
         .await
         .unwrap()
         .collect::<Vec<_>>()
         .await;
+    assert!(!chunks.is_empty());
+    assert!(chunks.iter().all(Result::is_ok));
 }
 
 #[tokio::test]
 pub async fn create_completion() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_completion").build().unwrap();
     let args = openai_rust::completions::CompletionArguments::new(
         "gpt-3.5-turbo-instruct",
         "The quick brown fox".to_owned(),
@@ -73,7 +104,7 @@ pub async fn create_completion() {
 
 #[tokio::test]
 pub async fn create_completion_logprobs() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_completion_logprobs").build().unwrap();
     let mut args = openai_rust::completions::CompletionArguments::new(
         "gpt-3.5-turbo-instruct",
         "The quick brown fox".to_owned(),
@@ -84,7 +115,7 @@ pub async fn create_completion_logprobs() {
 
 #[tokio::test]
 pub async fn create_embeddings() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_embeddings").build().unwrap();
     let args = openai_rust::embeddings::EmbeddingsArguments::new(
         "text-embedding-ada-002",
         "The food was delicious and the waiter...".to_owned(),
@@ -99,13 +130,16 @@ pub async fn external_client() {
         .user_agent("My cool program")
         .build()
         .unwrap();
-    let c = openai_rust::Client::new_with_client(&KEY, req_c);
+    let c = client("external_client")
+        .with_http_client(req_c)
+        .build()
+        .unwrap();
     c.list_models(None).await.unwrap();
 }
 
 #[tokio::test]
 pub async fn create_image() {
-    let c = openai_rust::Client::new(&KEY);
+    let c = client("create_image").build().unwrap();
     let args = openai_rust::images::ImageArguments::new(
         "A highly detailed, photorealistic depiction of a futuristic AI compute cluster covering an entire planet's surface, with glowing server towers, intricate cooling systems, and a starry night sky. The scene is vibrant, with sharp details, realistic lighting, and a sense of immense scale. Render in 4K resolution with a cinematic composition."
     );
@@ -114,7 +148,7 @@ pub async fn create_image() {
     if let Some(base64_image) = base64_images.first() {
         let image_bytes = general_purpose::STANDARD.decode(base64_image).unwrap();
 
-        let mut file = File::create("generated_image.png").unwrap();
+        let mut file = File::create(std::env::temp_dir().join("generated_image.png")).unwrap();
         file.write_all(&image_bytes).unwrap();
     }
 }
@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use openai_rust::chat::{ChatArguments, LocalTool, Message, ToolOutcome, ToolRunner};
+use openai_rust::middleware::{from_fn, Response};
+use openai_rust2 as openai_rust;
+use serde_json::{json, Value};
+
+fn completion(message: Value) -> String {
+    json!({
+        "id": "c", "created": 1, "model": "gpt-4o",
+        "choices": [{"index": 0, "message": message, "finish_reason": "stop"}],
+        "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2},
+    })
+    .to_string()
+}
+
+fn calls(calls: &[(&str, &str, &str)]) -> Value {
+    let calls: Vec<_> = calls
+        .iter()
+        .map(|(id, name, arguments)| {
+            json!({"id": id, "type": "function", "function": {"name": name, "arguments": arguments}})
+        })
+        .collect();
+    json!({"role": "assistant", "content": null, "tool_calls": calls})
+}
+
+/// A client answering with `responses` in turn, repeating the last one, and the request bodies it received.
+fn scripted(responses: Vec<Value>) -> (openai_rust::Client, Arc<Mutex<Vec<Value>>>) {
+    let responses = Arc::new(Mutex::new(VecDeque::from(responses)));
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(move |req, _| {
+            let (responses, log) = (responses.clone(), log.clone());
+            Box::pin(async move {
+                log.lock().unwrap().push(req.json::<Value>()?.unwrap());
+                let mut responses = responses.lock().unwrap();
+                let message = match responses.len() {
+                    1 => responses[0].clone(),
+                    _ => responses.pop_front().unwrap(),
+                };
+                Ok(Response::new(200, completion(message)))
+            })
+        }))
+        .build()
+        .unwrap();
+    (c, requests)
+}
+
+fn args() -> ChatArguments {
+    ChatArguments::new("gpt-4o", vec![Message::user("Weather in Oslo and Bergen?")])
+}
+
+fn weather() -> LocalTool {
+    LocalTool::new(
+        "get_weather",
+        "The current weather in a city.",
+        json!({
+            "type": "object",
+            "properties": {"city": {"type": "string", "enum": ["Oslo", "Bergen"]}},
+            "required": ["city"],
+            "additionalProperties": false,
+        }),
+        |args: Value| async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok::<_, String>(json!({"city": args["city"], "sky": "rain"}))
+        },
+    )
+}
+
+#[tokio::test]
+pub async fn runs_tools_until_the_model_answers() {
+    let (c, requests) = scripted(vec![
+        calls(&[
+            ("1", "get_weather", r#"{"city":"Oslo"}"#),
+            ("2", "get_weather", r#"{"city":"Bergen"}"#),
+            ("3", "get_weather", r#"{"town":"Oslo"}"#),
+            ("4", "get_time", "{}"),
+            ("5", "fail", ""),
+            ("6", "slow", ""),
+        ]),
+        json!({"role": "assistant", "content": "Rain in both."}),
+    ]);
+    let runner = ToolRunner::new()
+        .with_tool(weather())
+        .with_tool(LocalTool::new(
+            "fail",
+            "Fails.",
+            json!({"type": "object"}),
+            |_| async { Err::<Value, _>("out of order") },
+        ))
+        .with_tool(
+            LocalTool::new(
+                "slow",
+                "Never ends.",
+                json!({"type": "object"}),
+                |_| async {
+                    tokio::time::sleep(Duration::from_secs(60)).await;
+                    Ok::<_, String>("late")
+                },
+            )
+            .with_timeout(Duration::from_millis(300)),
+        );
+
+    let started = Instant::now();
+    let run = runner.run(&c, args()).await.unwrap();
+    // The 200ms calls and the 300ms timeout overlap.
+    assert!(
+        started.elapsed() < Duration::from_millis(450),
+        "{:?}",
+        started.elapsed()
+    );
+
+    assert_eq!(run.completion.to_string(), "Rain in both.");
+    assert!(!run.exhausted);
+    assert_eq!(run.steps.len(), 1);
+    let outcomes: Vec<_> = run.steps[0]
+        .calls
+        .iter()
+        .map(|r| r.outcome.clone())
+        .collect();
+    assert_eq!(
+        outcomes[0],
+        ToolOutcome::Ok(json!({"city": "Oslo", "sky": "rain"}))
+    );
+    assert_eq!(
+        outcomes[1],
+        ToolOutcome::Ok(json!({"city": "Bergen", "sky": "rain"}))
+    );
+    assert_eq!(
+        outcomes[2],
+        ToolOutcome::InvalidArguments("$: missing property city".to_owned())
+    );
+    assert_eq!(outcomes[3], ToolOutcome::UnknownTool);
+    assert_eq!(outcomes[4], ToolOutcome::Failed("out of order".to_owned()));
+    assert_eq!(
+        outcomes[5],
+        ToolOutcome::TimedOut(Duration::from_millis(300))
+    );
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    let names: Vec<_> = requests[0]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["function"]["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["fail", "get_weather", "slow"]);
+    let sent = requests[1]["messages"].as_array().unwrap();
+    assert_eq!(sent.len(), 8);
+    assert_eq!(
+        sent[2],
+        json!({"role": "tool", "tool_call_id": "1", "content": r#"{"city":"Oslo","sky":"rain"}"#})
+    );
+    assert_eq!(sent[6]["content"], "error: out of order");
+    assert_eq!(run.messages.len(), 9);
+}
+
+#[tokio::test]
+pub async fn stops_at_the_iteration_limit() {
+    let (c, requests) = scripted(vec![calls(&[("1", "get_weather", r#"{"city":"Oslo"}"#)])]);
+    let run = ToolRunner::new()
+        .with_tool(weather())
+        .with_max_iterations(3)
+        .with_parallel(false)
+        .run(&c, args())
+        .await
+        .unwrap();
+    assert!(run.exhausted);
+    assert_eq!(run.steps.len(), 2);
+    assert_eq!(requests.lock().unwrap().len(), 3);
+    assert!(run.completion.choices[0].message.tool_calls.is_some());
+}
+
+#[tokio::test]
+pub async fn nested_arguments_are_checked_against_their_definitions() {
+    let (c, _) = scripted(vec![
+        calls(&[
+            ("1", "book", r#"{"trip":{"from":"Oslo","to":"Bergen"}}"#),
+            (
+                "2",
+                "book",
+                r#"{"trip":{"from":"Oslo","to":"Bergen","by":"air"}}"#,
+            ),
+            ("3", "book", r#"{"trip":{"from":"Oslo"}}"#),
+        ]),
+        json!({"role": "assistant", "content": "Booked."}),
+    ]);
+    let book = LocalTool::new(
+        "book",
+        "Book a trip.",
+        json!({
+            "type": "object",
+            "properties": {"trip": {"$ref": "#/$defs/Trip"}},
+            "required": ["trip"],
+            "additionalProperties": false,
+            "$defs": {"Trip": {
+                "type": "object",
+                "properties": {"from": {"type": "string"}, "to": {"type": "string"}},
+                "required": ["from", "to"],
+                "additionalProperties": false,
+            }},
+        }),
+        |_: Value| async move { Ok::<_, String>("booked") },
+    );
+    let run = ToolRunner::new()
+        .with_tool(book)
+        .run(&c, args())
+        .await
+        .unwrap();
+    let outcomes: Vec<_> = run.steps[0]
+        .calls
+        .iter()
+        .map(|r| r.outcome.clone())
+        .collect();
+    assert_eq!(
+        outcomes,
+        [
+            ToolOutcome::Ok(json!("booked")),
+            ToolOutcome::InvalidArguments("$.trip: unexpected property by".to_owned()),
+            ToolOutcome::InvalidArguments("$.trip: missing property to".to_owned()),
+        ]
+    );
+}
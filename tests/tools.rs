@@ -0,0 +1,110 @@
+use std::sync::{Arc, Mutex};
+
+use openai_rust::chat::{ChatArguments, Message, OpenAITool, Tool, ToolChoice};
+use openai_rust::middleware::{from_fn, Response};
+use openai_rust2 as openai_rust;
+use serde_json::json;
+
+fn weather() -> Tool {
+    Tool::function(
+        "get_weather",
+        "The current weather in a city.",
+        json!({"type": "object", "properties": {"city": {"type": "string"}}, "required": ["city"]}),
+    )
+}
+
+#[test]
+pub fn tool_arguments_are_serialized() {
+    let args = ChatArguments::new("gpt-4o", vec![Message::user("Weather in Oslo?")])
+        .with_tools(vec![weather()])
+        .with_tool_choice(ToolChoice::Function("get_weather".to_owned()))
+        .with_parallel_tool_calls(false);
+    let json = serde_json::to_value(&args).unwrap();
+    assert_eq!(json["tools"][0]["type"], "function");
+    assert_eq!(json["tools"][0]["function"]["name"], "get_weather");
+    assert_eq!(
+        json["tools"][0]["function"]["parameters"]["required"][0],
+        "city"
+    );
+    assert_eq!(
+        json["tool_choice"],
+        json!({"type": "function", "function": {"name": "get_weather"}})
+    );
+    assert_eq!(json["parallel_tool_calls"], false);
+
+    for (choice, expected) in [
+        (ToolChoice::None, "none"),
+        (ToolChoice::Auto, "auto"),
+        (ToolChoice::Required, "required"),
+    ] {
+        assert_eq!(serde_json::to_value(choice).unwrap(), expected);
+    }
+
+    let args =
+        ChatArguments::new("gpt-4o", vec![]).with_openai_tools(vec![OpenAITool::web_search()]);
+    let json = serde_json::to_value(&args).unwrap();
+    assert_eq!(json["tools"][0]["type"], "web_search");
+    assert!(json.get("tool_choice").is_none());
+}
+
+#[tokio::test]
+pub async fn tool_calls_round_trip() {
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = requests.clone();
+    let c = openai_rust::Client::builder()
+        .with_middleware(from_fn(move |req, _| {
+            let log = log.clone();
+            Box::pin(async move {
+                let body = req.json::<serde_json::Value>()?.unwrap();
+                let answered = body["messages"].as_array().unwrap().len() > 1;
+                log.lock().unwrap().push(body);
+                let message = if answered {
+                    json!({"role": "assistant", "content": "It is sunny in Oslo."})
+                } else {
+                    json!({"role": "assistant", "content": null, "tool_calls": [{
+                        "id": "call_1", "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"},
+                    }]})
+                };
+                let finish_reason = if answered { "stop" } else { "tool_calls" };
+                let completion = json!({
+                    "id": "c", "created": 1, "model": "gpt-4o",
+                    "choices": [{"index": 0, "message": message, "finish_reason": finish_reason}],
+                    "usage": {"prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2},
+                });
+                Ok(Response::new(200, completion.to_string()))
+            })
+        }))
+        .build()
+        .unwrap();
+
+    let mut messages = vec![Message::user("Weather in Oslo?")];
+    let args = ChatArguments::new("gpt-4o", messages.clone()).with_tools(vec![weather()]);
+    let res = c.create_chat(args, None).await.unwrap();
+    let assistant = res.choices[0].message.clone();
+    assert!(assistant.content.is_none());
+    assert_eq!(res.to_string(), "");
+    let call = &assistant.tool_calls.as_ref().unwrap()[0];
+    assert_eq!(call.function.name, "get_weather");
+    let arguments: serde_json::Value = call.parse_arguments().unwrap();
+    assert_eq!(arguments["city"], "Oslo");
+
+    let result = Message::tool_result(call, r#"{"sky":"sunny"}"#);
+    messages.extend([assistant, result]);
+    let args = ChatArguments::new("gpt-4o", messages).with_tools(vec![weather()]);
+    let res = c.create_chat(args, None).await.unwrap();
+    assert_eq!(res.to_string(), "It is sunny in Oslo.");
+
+    let sent = &requests.lock().unwrap()[1]["messages"];
+    assert_eq!(
+        sent[1],
+        json!({"role": "assistant", "content": null, "tool_calls": [{
+            "id": "call_1", "type": "function",
+            "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"},
+        }]})
+    );
+    assert_eq!(
+        sent[2],
+        json!({"role": "tool", "content": "{\"sky\":\"sunny\"}", "tool_call_id": "call_1"})
+    );
+}
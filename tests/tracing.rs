@@ -0,0 +1,147 @@
+#![cfg(feature = "tracing")]
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use openai_rust::futures_util::StreamExt;
+use openai_rust2 as openai_rust;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::Layer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+mod common;
+use common::{chat_args, CHAT_COMPLETION, CHAT_STREAM};
+
+type Fields = HashMap<String, String>;
+
+/// Collects the fields of every span when it closes.
+#[derive(Clone, Default)]
+struct Spans {
+    open: Arc<Mutex<HashMap<Id, Fields>>>,
+    closed: Arc<Mutex<Vec<Fields>>>,
+}
+
+struct Visitor<'a>(&'a mut Fields);
+
+impl Visit for Visitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_owned(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_owned(), value.to_owned());
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for Spans {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, _: Context<'_, S>) {
+        let mut fields = Fields::new();
+        attrs.record(&mut Visitor(&mut fields));
+        self.open.lock().unwrap().insert(id.clone(), fields);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _: Context<'_, S>) {
+        if let Some(fields) = self.open.lock().unwrap().get_mut(id) {
+            values.record(&mut Visitor(fields));
+        }
+    }
+
+    fn on_close(&self, id: Id, _: Context<'_, S>) {
+        if let Some(fields) = self.open.lock().unwrap().remove(&id) {
+            self.closed.lock().unwrap().push(fields);
+        }
+    }
+}
+
+#[tokio::test]
+pub async fn chat_span_records_status_request_id_and_usage() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-request-id", "req_123")
+                .set_body_string(CHAT_COMPLETION),
+        )
+        .mount(&server)
+        .await;
+
+    let spans = Spans::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    c.create_chat(chat_args(), None).await.unwrap();
+
+    let closed = spans.closed.lock().unwrap();
+    assert_eq!(closed.len(), 1);
+    let span = &closed[0];
+    assert_eq!(span["endpoint"], "chat/completions");
+    assert_eq!(span["model"], "gpt-3.5-turbo");
+    assert_eq!(span["status"], "200");
+    assert_eq!(span["request_id"], "req_123");
+    assert_eq!(span["prompt_tokens"], "9");
+    assert_eq!(span["completion_tokens"], "2");
+    assert_eq!(span["total_tokens"], "11");
+    assert!(span.contains_key("latency_ms"));
+}
+
+#[tokio::test]
+pub async fn error_span_records_status() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/models"))
+        .respond_with(ResponseTemplate::new(404).insert_header("x-request-id", "req_404"))
+        .mount(&server)
+        .await;
+
+    let spans = Spans::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    c.list_models(None).await.unwrap_err();
+
+    let closed = spans.closed.lock().unwrap();
+    let span = &closed[0];
+    assert_eq!(span["endpoint"], "models");
+    assert_eq!(span["status"], "404");
+    assert_eq!(span["request_id"], "req_404");
+}
+
+#[tokio::test]
+pub async fn stream_span_closes_when_the_stream_ends() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/chat/completions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("content-type", "text/event-stream")
+                .set_body_string(CHAT_STREAM),
+        )
+        .mount(&server)
+        .await;
+
+    let spans = Spans::default();
+    let _guard =
+        tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+    let c = openai_rust::Client::new_with_base_url("", &server.uri());
+    let mut stream = c.create_chat_stream(chat_args(), None).await.unwrap();
+    assert!(spans.closed.lock().unwrap().is_empty());
+
+    let mut chunks = 0;
+    while let Some(chunk) = stream.next().await {
+        chunk.unwrap();
+        chunks += 1;
+    }
+    assert_eq!(chunks, 2);
+
+    let closed = spans.closed.lock().unwrap();
+    assert_eq!(closed.len(), 1);
+    let span = &closed[0];
+    assert_eq!(span["status"], "200");
+    assert_eq!(span["chunks"], "2");
+    assert!(span.contains_key("time_to_first_chunk_ms"));
+}